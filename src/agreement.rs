@@ -0,0 +1,236 @@
+use crate::{
+    error::Error,
+    rating::{Ranking, RatingOverrides},
+    rel_vec::RelVec,
+};
+
+/// Number of entries listed with the most disagreement
+const DISAGREEMENT: usize = 5;
+
+/// Ranks of values, 1 for the highest. Tied values share their average rank.
+pub fn ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[b].total_cmp(&values[a]));
+
+    let mut ranks = vec![0.0; values.len()];
+    let mut i = 0;
+    while i < order.len() {
+        let mut j = i;
+        while j + 1 < order.len() && values[order[j + 1]] == values[order[i]] {
+            j += 1;
+        }
+        let rank = (i + j) as f64 / 2.0 + 1.0;
+        for &k in &order[i..=j] {
+            ranks[k] = rank;
+        }
+        i = j + 1;
+    }
+
+    ranks
+}
+
+/// Kendall's tau-b of two rankings, NaN if one of them ties everything
+pub fn kendall_tau(x: &[f64], y: &[f64]) -> f64 {
+    let (mut concordant, mut discordant, mut ties_x, mut ties_y) = (0.0, 0.0, 0.0, 0.0);
+
+    for i in 0..x.len() {
+        for j in i + 1..x.len() {
+            let dx = x[i] - x[j];
+            let dy = y[i] - y[j];
+            if dx == 0.0 {
+                ties_x += 1.0;
+            }
+            if dy == 0.0 {
+                ties_y += 1.0;
+            }
+            if dx * dy > 0.0 {
+                concordant += 1.0;
+            } else if dx * dy < 0.0 {
+                discordant += 1.0;
+            }
+        }
+    }
+
+    let pairs = (x.len() * x.len().saturating_sub(1) / 2) as f64;
+    (concordant - discordant) / ((pairs - ties_x) * (pairs - ties_y)).sqrt()
+}
+
+/// Spearman's rho of two rankings, NaN if one of them ties everything
+pub fn spearman_rho(x: &[f64], y: &[f64]) -> f64 {
+    let (rx, ry) = (ranks(x), ranks(y));
+    let n = rx.len() as f64;
+    let mean = (n + 1.0) / 2.0;
+
+    let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for (a, b) in rx.iter().zip(&ry) {
+        cov += (a - mean) * (b - mean);
+        var_x += (a - mean) * (a - mean);
+        var_y += (b - mean) * (b - mean);
+    }
+
+    cov / (var_x * var_y).sqrt()
+}
+
+/// Kendall's coefficient of concordance of several rankings of the same
+/// entries, given as ranks, with the correction for ties
+pub fn kendall_w(rankings: &[Vec<f64>]) -> f64 {
+    let m = rankings.len() as f64;
+    let n = rankings.first().map_or(0, |r| r.len());
+    let mean = m * (n as f64 + 1.0) / 2.0;
+
+    let s: f64 = (0..n)
+        .map(|i| rankings.iter().map(|r| r[i]).sum::<f64>() - mean)
+        .map(|d| d * d)
+        .sum();
+    let ties: f64 = rankings
+        .iter()
+        .map(|r| {
+            let mut sorted = r.clone();
+            sorted.sort_by(f64::total_cmp);
+            sorted
+                .chunk_by(|a, b| a == b)
+                .map(|g| {
+                    let t = g.len() as f64;
+                    t * t * t - t
+                })
+                .sum::<f64>()
+        })
+        .sum();
+    let n = n as f64;
+
+    12.0 * s / (m * m * (n * n * n - n) - m * ties)
+}
+
+/// Compares the rankings of all voters of a list
+pub(crate) fn agreement(
+    input: &str,
+    ranking: Ranking,
+    overrides: RatingOverrides,
+) -> Result<(), Error> {
+    let mut rv = RelVec::load(input)?;
+    overrides.apply(&mut rv.config);
+
+    let voters = rv.voters();
+    if voters.len() < 2 {
+        println!("There are less than two voters.");
+        return Ok(());
+    }
+    let lists: Vec<RelVec> = voters.iter().map(|v| rv.of_voter(v)).collect();
+    let value = |l: &RelVec, i: usize| ranking.value(&l[i], &rv.config);
+    let voted = |l: &RelVec, i: usize| l[i].votes > 0;
+
+    for (v, l) in voters.iter().zip(&lists) {
+        let mut order: Vec<usize> = (0..l.len()).filter(|&i| voted(l, i)).collect();
+        order.sort_by(|&x, &y| ranking.compare(&l[x], &l[y], &rv.config).reverse());
+        let names: Vec<&str> = order.iter().map(|&i| l[i].name.as_str()).collect();
+        println!("\x1b[34m{}\x1b[0m: {}", v, names.join(", "));
+    }
+    println!();
+
+    for x in 0..lists.len() {
+        for y in x + 1..lists.len() {
+            let common: Vec<usize> = (0..rv.len())
+                .filter(|&i| voted(&lists[x], i) && voted(&lists[y], i))
+                .collect();
+            let vx: Vec<f64> = common.iter().map(|&i| value(&lists[x], i)).collect();
+            let vy: Vec<f64> = common.iter().map(|&i| value(&lists[y], i)).collect();
+
+            println!(
+                "{} ~ {}: τ \x1b[34m{:.3}\x1b[0m, ρ \x1b[34m{:.3}\x1b[0m \x1b[90m({} common entries)\x1b[0m",
+                voters[x],
+                voters[y],
+                kendall_tau(&vx, &vy),
+                spearman_rho(&vx, &vy),
+                common.len()
+            );
+        }
+    }
+
+    // Only entries every voter voted on can be compared by all
+    let common: Vec<usize> = (0..rv.len())
+        .filter(|&i| lists.iter().all(|l| voted(l, i)))
+        .collect();
+    if common.len() < 2 {
+        println!();
+        println!("There are less than two entries every voter voted on.");
+        return Ok(());
+    }
+    let rankings: Vec<Vec<f64>> = lists
+        .iter()
+        .map(|l| ranks(&common.iter().map(|&i| value(l, i)).collect::<Vec<_>>()))
+        .collect();
+
+    println!();
+    println!(
+        "Kendall's W: \x1b[34m{:.3}\x1b[0m \x1b[90m({} entries every voter voted on)\x1b[0m",
+        kendall_w(&rankings),
+        common.len()
+    );
+
+    // Standard deviation of the ranks of each entry
+    let mut spread: Vec<(usize, f64)> = (0..common.len())
+        .map(|k| {
+            let m = rankings.len() as f64;
+            let mean = rankings.iter().map(|r| r[k]).sum::<f64>() / m;
+            let var = rankings.iter().map(|r| (r[k] - mean).powi(2)).sum::<f64>() / m;
+            (k, var.sqrt())
+        })
+        .collect();
+    spread.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    println!();
+    println!("Most disagreement:");
+    for &(k, sd) in spread.iter().take(DISAGREEMENT) {
+        let places: Vec<String> = voters
+            .iter()
+            .zip(&rankings)
+            .map(|(v, r)| format!("{} #{}", v, r[k]))
+            .collect();
+        println!(
+            "{} \x1b[90m- σ {:.2} - {}\x1b[0m",
+            rv[common[k]].name,
+            sd,
+            places.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{kendall_tau, kendall_w, ranks, spearman_rho};
+
+    #[test]
+    fn agreement_ranks() {
+        assert_eq!(ranks(&[0.5, 0.9, 0.1]), [2.0, 1.0, 3.0]);
+        assert_eq!(ranks(&[1.0, 3.0, 3.0, 0.0]), [3.0, 1.5, 1.5, 4.0]);
+    }
+
+    #[test]
+    fn agreement_kendall_tau() {
+        let x = [1.0, 2.0, 3.0, 4.0, 5.0];
+
+        assert!((kendall_tau(&x, &[3.0, 4.0, 1.0, 2.0, 5.0]) - 0.2).abs() < 1e-9);
+        assert_eq!(kendall_tau(&x, &x), 1.0);
+        assert_eq!(kendall_tau(&x, &[5.0, 4.0, 3.0, 2.0, 1.0]), -1.0);
+        assert!(kendall_tau(&x, &[1.0; 5]).is_nan());
+    }
+
+    #[test]
+    fn agreement_spearman_rho() {
+        let x = [1.0, 2.0, 3.0, 4.0, 5.0];
+
+        assert!((spearman_rho(&x, &[3.0, 4.0, 1.0, 2.0, 5.0]) - 0.2).abs() < 1e-9);
+        assert!((spearman_rho(&x, &[10.0, 20.0, 30.0, 40.0, 50.0]) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn agreement_kendall_w() {
+        let a = vec![1.0, 2.0, 3.0];
+        let b = vec![3.0, 2.0, 1.0];
+
+        assert!((kendall_w(&[a.clone(), a.clone(), a.clone()]) - 1.0).abs() < 1e-9);
+        assert_eq!(kendall_w(&[a, b]), 0.0);
+    }
+}
@@ -0,0 +1,284 @@
+use std::{
+    env,
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{error::Error, history::datetime, rel_vec::RelVec, storage::Output};
+
+/// Number of backups of JSON files kept unless `--backups` or
+/// `$RELATY_BACKUPS` say otherwise
+pub const DEFAULT_BACKUPS: usize = 3;
+
+/// Number of backups to keep of every file set by `$RELATY_BACKUPS`, for
+/// when `--backups` isn't given. 0 disables them.
+pub fn count() -> Option<usize> {
+    env::var("RELATY_BACKUPS").ok().and_then(|n| n.parse().ok())
+}
+
+/// Path next to `path` with a suffix added to the file name
+pub fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path
+        .file_name()
+        .map_or_else(OsString::new, |n| n.to_owned());
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Path of the `n`th backup of a file, 1 is the newest
+pub fn path(file: &Path, n: usize) -> PathBuf {
+    sibling(file, &format!(".bak.{}", n))
+}
+
+/// Existing backups of a file with the time they were saved, newest first
+pub fn backups(file: &Path) -> Vec<(usize, PathBuf, Option<SystemTime>)> {
+    (1..)
+        .map(|n| (n, path(file, n)))
+        .take_while(|(_, p)| p.exists())
+        .map(|(n, p)| {
+            let time = fs::metadata(&p).and_then(|m| m.modified()).ok();
+            (n, p, time)
+        })
+        .collect()
+}
+
+/// Moves every backup one place back, the last one is dropped
+fn shift(file: &Path, keep: usize) -> Result<(), Error> {
+    for n in (1..keep).rev() {
+        let from = path(file, n);
+        if from.exists() {
+            fs::rename(from, path(file, n + 1))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies a file and keeps the time it was changed. Backups keep the time
+/// the file was saved.
+fn copy(file: &Path, to: &Path) -> Result<(), Error> {
+    fs::copy(file, to)?;
+    if let Ok(time) = fs::metadata(file).and_then(|m| m.modified()) {
+        File::options().write(true).open(to)?.set_modified(time)?;
+    }
+
+    Ok(())
+}
+
+/// Makes a rename in the directory of `file` durable
+#[cfg(unix)]
+fn sync_dir(file: &Path) {
+    let dir = match file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_file: &Path) {}
+
+/// Writes a file so that it is either completely written or unchanged: the
+/// content goes to a temporary file that replaces the file once it's on disk.
+/// The replaced file keeps its permissions and its content is kept as backup.
+pub fn write<F>(file: &Path, keep: usize, content: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut dyn Write) -> Result<(), Error>,
+{
+    let tmp = sibling(file, ".tmp");
+
+    let result = File::create(&tmp)
+        .map_err(Error::from)
+        .and_then(|f| {
+            if let Ok(metadata) = fs::metadata(file) {
+                f.set_permissions(metadata.permissions())?;
+            }
+
+            let mut writer = BufWriter::new(&f);
+            content(&mut writer)?;
+            writer.flush()?;
+            drop(writer);
+            f.sync_all()?;
+            Ok(())
+        })
+        .and_then(|_| {
+            update(file, keep, || {
+                fs::rename(&tmp, file)?;
+                sync_dir(file);
+                Ok(())
+            })
+        });
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+
+    result
+}
+
+/// Changes or replaces a file. The previous content is kept as backup if
+/// the change succeeds, otherwise the backups stay as they are.
+pub fn update<F>(file: &Path, keep: usize, change: F) -> Result<(), Error>
+where
+    F: FnOnce() -> Result<(), Error>,
+{
+    let keep = if file.exists() { keep } else { 0 };
+    let copy_path = sibling(file, ".bak.tmp");
+    if keep > 0 {
+        copy(file, &copy_path)?;
+    }
+
+    if let Err(e) = change() {
+        let _ = fs::remove_file(&copy_path);
+        return Err(e);
+    }
+
+    if keep > 0 {
+        shift(file, keep)?;
+        fs::rename(&copy_path, path(file, 1))?;
+    }
+
+    Ok(())
+}
+
+/// Replaces a list file by one of its backups. Without a number the backups
+/// are listed to choose one. The replaced file becomes the newest backup.
+pub(crate) fn restore(output: Output, number: Option<usize>) -> Result<(), Error> {
+    let file = output.path;
+    let backups = backups(Path::new(file));
+    if backups.is_empty() {
+        println!("There are no backups of {}.", file);
+        return Ok(());
+    }
+
+    let number = match number {
+        Some(number) => number,
+        None => {
+            for (n, p, time) in &backups {
+                let time = time
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map_or_else(|| "unknown time".to_owned(), |d| datetime(d.as_secs()));
+                let summary = match RelVec::load(p) {
+                    Ok(rv) => format!(
+                        "{} entries, {} votes",
+                        rv.len(),
+                        rv.iter().map(|e| e.votes).sum::<u32>() / 2
+                    ),
+                    Err(e) => format!("\x1b[31m{}\x1b[0m", e),
+                };
+                println!("\x1b[34m{}\x1b[0m {} - {}", n, time, summary);
+            }
+
+            println!();
+            println!("Which backup do you want to restore? [1-{}]", backups.len());
+            let mut ans = String::new();
+            io::stdin().read_line(&mut ans)?;
+            match ans.trim().parse() {
+                Ok(n) => n,
+                Err(_) => {
+                    println!("Nothing restored");
+                    return Ok(());
+                }
+            }
+        }
+    };
+
+    let backup = match backups.iter().find(|(n, _, _)| *n == number) {
+        Some((_, p, _)) => p,
+        None => {
+            println!("\x1b[31mThere is no backup {}\x1b[0m", number);
+            return Ok(());
+        }
+    };
+
+    // Read before saving, saving moves the backups
+    let mut rv = RelVec::load(backup)?;
+    rv.save_to(output)?;
+    println!("Restored backup {} of {}", number, file);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, io::Write};
+
+    use super::{backups, path, write};
+    use crate::error::Error;
+
+    #[test]
+    fn backup_write() {
+        let file = env::temp_dir().join("_relaty_backup_write.json");
+        let content = |s: &'static str| move |w: &mut dyn Write| Ok(w.write_all(s.as_bytes())?);
+
+        for s in ["a", "b", "c", "d"] {
+            write(&file, 2, content(s)).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&file).unwrap(), "d");
+        assert_eq!(fs::read_to_string(path(&file, 1)).unwrap(), "c");
+        assert_eq!(fs::read_to_string(path(&file, 2)).unwrap(), "b");
+        assert_eq!(backups(&file).len(), 2);
+
+        // A failed write keeps everything as it was
+        let failed = write(&file, 2, |_| Err(Error::ArgError));
+        assert!(failed.is_err());
+        assert_eq!(fs::read_to_string(&file).unwrap(), "d");
+        assert_eq!(fs::read_to_string(path(&file, 1)).unwrap(), "c");
+
+        for p in [file.clone(), path(&file, 1), path(&file, 2)] {
+            fs::remove_file(p).unwrap();
+        }
+        assert!(!file.with_extension("json.tmp").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn backup_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let file = env::temp_dir().join("_relaty_backup_permissions.json");
+        write(&file, 0, |w| Ok(w.write_all(b"a")?)).unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o660)).unwrap();
+
+        write(&file, 0, |w| Ok(w.write_all(b"b")?)).unwrap();
+        let mode = fs::metadata(&file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o660);
+
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn backup_failed_replace() {
+        let file = env::temp_dir().join("_relaty_backup_failed_replace.json");
+        let _ = fs::remove_dir_all(&file);
+        fs::write(path(&file, 1), "a").unwrap();
+
+        // A directory can't be replaced by a file
+        fs::create_dir_all(file.join("x")).unwrap();
+        assert!(write(&file, 2, |w| Ok(w.write_all(b"b")?)).is_err());
+        assert_eq!(fs::read_to_string(path(&file, 1)).unwrap(), "a");
+        assert!(!path(&file, 2).exists());
+        assert!(!file.with_extension("json.tmp").exists());
+
+        fs::remove_dir_all(&file).unwrap();
+        fs::remove_file(path(&file, 1)).unwrap();
+    }
+
+    #[test]
+    fn backup_none() {
+        let file = env::temp_dir().join("_relaty_backup_none.json");
+
+        write(&file, 0, |w| Ok(w.write_all(b"a")?)).unwrap();
+        write(&file, 0, |w| Ok(w.write_all(b"b")?)).unwrap();
+
+        assert_eq!(fs::read_to_string(&file).unwrap(), "b");
+        assert!(backups(&file).is_empty());
+
+        fs::remove_file(file).unwrap();
+    }
+}
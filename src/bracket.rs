@@ -0,0 +1,606 @@
+use rand::prelude::SliceRandom;
+
+use crate::{
+    error::Error,
+    history::Outcome,
+    rating::{Ranking, RatingOverrides},
+    rel_vec::RelVec,
+    storage::Output,
+    vote::{read_command, show_pair},
+};
+
+/// Where an entrant of a match comes from
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Slot {
+    /// Entry with the given seed, 0 is the best one
+    Seed(usize),
+    Winner(usize),
+    Loser(usize),
+    /// Nobody, the other entrant advances
+    Bye,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Side {
+    Winners,
+    Losers,
+    Final,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Match {
+    slots: [Slot; 2],
+    side: Side,
+    round: usize,
+    /// Whether the first entrant won, `None` if it wasn't played yet
+    result: Option<bool>,
+    /// Only played if the first entrant lost the match it comes from
+    reset: bool,
+}
+
+/// A single or double elimination bracket. Matches are stored in the order
+/// they can be played. An unfinished bracket is kept in the list file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "BracketFile", into = "BracketFile")]
+pub struct Bracket {
+    names: Vec<String>,
+    matches: Vec<Match>,
+    /// Number of rounds of the winners bracket
+    rounds: usize,
+}
+
+/// Bracket as stored in the list file. The matches follow from the names.
+#[derive(Serialize, Deserialize)]
+struct BracketFile {
+    names: Vec<String>,
+    double: bool,
+    results: Vec<Option<bool>>,
+}
+
+impl From<BracketFile> for Bracket {
+    fn from(file: BracketFile) -> Self {
+        let mut bracket = Bracket::new(file.names, file.double);
+        for (m, result) in bracket.matches.iter_mut().zip(file.results) {
+            m.result = result;
+        }
+        bracket
+    }
+}
+
+impl From<Bracket> for BracketFile {
+    fn from(bracket: Bracket) -> Self {
+        Self {
+            double: bracket.matches.iter().any(|m| m.side == Side::Final),
+            results: bracket.matches.iter().map(|m| m.result).collect(),
+            names: bracket.names,
+        }
+    }
+}
+
+/// Seeds of the first round from top to bottom, so that the best seeds meet
+/// as late as possible
+fn seed_order(size: usize) -> Vec<usize> {
+    let mut order = vec![0];
+
+    while order.len() < size {
+        let n = order.len() * 2;
+        order = order.into_iter().flat_map(|s| [s, n - 1 - s]).collect();
+    }
+
+    order
+}
+
+impl Bracket {
+    /// Bracket for the given names, best seed first
+    pub fn new(names: Vec<String>, double: bool) -> Self {
+        let rounds = names.len().max(2).next_power_of_two().trailing_zeros() as usize;
+        let mut matches = Vec::new();
+        let mut add = |slots: [Slot; 2], side: Side, round: usize| {
+            matches.push(Match {
+                slots,
+                side,
+                round,
+                result: None,
+                reset: false,
+            });
+            matches.len() - 1
+        };
+
+        let seed = |s: usize| {
+            if s < names.len() {
+                Slot::Seed(s)
+            } else {
+                Slot::Bye
+            }
+        };
+        let mut winners: Vec<Vec<usize>> = vec![seed_order(1 << rounds)
+            .chunks(2)
+            .map(|p| add([seed(p[0]), seed(p[1])], Side::Winners, 1))
+            .collect()];
+        for round in 2..=rounds {
+            let previous = winners[winners.len() - 1].clone();
+            winners.push(
+                previous
+                    .chunks(2)
+                    .map(|p| {
+                        add(
+                            [Slot::Winner(p[0]), Slot::Winner(p[1])],
+                            Side::Winners,
+                            round,
+                        )
+                    })
+                    .collect(),
+            );
+        }
+        let champion = Slot::Winner(winners[rounds - 1][0]);
+
+        if double {
+            let mut losers: Vec<Slot> = winners[0].iter().map(|&m| Slot::Loser(m)).collect();
+            let mut round = 0;
+
+            if rounds > 1 {
+                round += 1;
+                losers = losers
+                    .chunks(2)
+                    .map(|p| Slot::Winner(add([p[0], p[1]], Side::Losers, round)))
+                    .collect();
+            }
+            for r in 2..=rounds {
+                // Losers drop in, reversed every other round to avoid rematches
+                let mut dropped: Vec<Slot> =
+                    winners[r - 1].iter().map(|&m| Slot::Loser(m)).collect();
+                if r % 2 == 0 {
+                    dropped.reverse();
+                }
+                round += 1;
+                losers = losers
+                    .iter()
+                    .zip(dropped)
+                    .map(|(&a, b)| Slot::Winner(add([a, b], Side::Losers, round)))
+                    .collect();
+
+                if r < rounds {
+                    round += 1;
+                    losers = losers
+                        .chunks(2)
+                        .map(|p| Slot::Winner(add([p[0], p[1]], Side::Losers, round)))
+                        .collect();
+                }
+            }
+
+            let first = add([champion, losers[0]], Side::Final, 1);
+            let second = add([Slot::Winner(first), Slot::Loser(first)], Side::Final, 2);
+            matches[second].reset = true;
+        }
+
+        Self {
+            names,
+            matches,
+            rounds,
+        }
+    }
+
+    /// Name in a slot, `Some(None)` if nobody and `None` if not known yet
+    fn entrant(&self, slot: Slot) -> Option<Option<&str>> {
+        match slot {
+            Slot::Seed(s) => Some(Some(&self.names[s])),
+            Slot::Winner(m) => self.winner(m),
+            Slot::Loser(m) => self.loser(m),
+            Slot::Bye => Some(None),
+        }
+    }
+
+    /// Both entrants if known and whether the match is decided without a game
+    fn entrants(&self, m: usize) -> Option<(Option<&str>, Option<&str>, bool)> {
+        let a = self.entrant(self.matches[m].slots[0])?;
+        let b = self.entrant(self.matches[m].slots[1])?;
+        // A reset is only needed if the winner of the winners bracket lost
+        let reset_needed = match self.matches[m].slots[0] {
+            Slot::Winner(p) if self.matches[m].reset => self.matches[p].result != Some(true),
+            _ => true,
+        };
+        let skipped = a.is_none() || b.is_none() || !reset_needed;
+
+        Some((a, b, skipped))
+    }
+
+    fn winner(&self, m: usize) -> Option<Option<&str>> {
+        let (a, b, skipped) = self.entrants(m)?;
+
+        if skipped {
+            return Some(a.or(b));
+        }
+        self.matches[m]
+            .result
+            .map(|first| if first { a } else { b })
+    }
+
+    fn loser(&self, m: usize) -> Option<Option<&str>> {
+        let (a, b, skipped) = self.entrants(m)?;
+
+        if skipped {
+            return Some(None);
+        }
+        self.matches[m]
+            .result
+            .map(|first| if first { b } else { a })
+    }
+
+    /// Next match to play with both entrants
+    pub fn next(&self) -> Option<(usize, &str, &str)> {
+        (0..self.matches.len()).find_map(|m| {
+            if self.matches[m].result.is_some() {
+                return None;
+            }
+            match self.entrants(m)? {
+                (Some(a), Some(b), false) => Some((m, a, b)),
+                _ => None,
+            }
+        })
+    }
+
+    /// Sets the result of a match, `None` to undo it
+    pub fn set(&mut self, m: usize, first: Option<bool>) {
+        self.matches[m].result = first;
+    }
+
+    /// Whether every entrant is still in the list
+    pub fn entrants_in(&self, rv: &RelVec) -> bool {
+        self.names.iter().all(|n| rv.position(n).is_some())
+    }
+
+    /// Winner of the whole bracket once every match was played
+    pub fn champion(&self) -> Option<&str> {
+        self.winner(self.matches.len() - 1).flatten()
+    }
+
+    /// Name of the round a match belongs to
+    pub fn round_name(&self, m: usize) -> String {
+        let m = &self.matches[m];
+
+        match m.side {
+            Side::Winners if m.round == self.rounds => {
+                if self.matches.iter().any(|m| m.side == Side::Final) {
+                    "Winners final".to_owned()
+                } else {
+                    "Final".to_owned()
+                }
+            }
+            Side::Winners => format!("Round {}", m.round),
+            Side::Losers => format!("Losers round {}", m.round),
+            Side::Final if m.reset => "Grand final reset".to_owned(),
+            Side::Final => "Grand final".to_owned(),
+        }
+    }
+
+    fn label(name: Option<Option<&str>>) -> &str {
+        match name {
+            Some(Some(n)) => n,
+            Some(None) => "-",
+            None => "?",
+        }
+    }
+
+    /// Draws the winners bracket as a tree and lists the other matches
+    pub fn render(&self) -> String {
+        let winners: Vec<usize> = (0..self.matches.len())
+            .filter(|&m| self.matches[m].side == Side::Winners)
+            .collect();
+        let first = 1usize << (self.rounds - 1);
+
+        // Names by column, the first one are the entrants of the first round
+        let mut columns: Vec<Vec<&str>> = vec![winners[..first]
+            .iter()
+            .flat_map(|&m| {
+                self.matches[m]
+                    .slots
+                    .iter()
+                    .map(|&s| Self::label(self.entrant(s)))
+                    .collect::<Vec<_>>()
+            })
+            .collect()];
+        let mut start = 0;
+        for round in 0..self.rounds {
+            let count = first >> round;
+            columns.push(
+                winners[start..start + count]
+                    .iter()
+                    .map(|&m| Self::label(self.winner(m)))
+                    .collect(),
+            );
+            start += count;
+        }
+
+        let width = columns
+            .iter()
+            .flatten()
+            .map(|n| n.chars().count())
+            .max()
+            .unwrap_or(1)
+            + 2;
+        let height = 2 * columns[0].len() - 1;
+        let mut grid = vec![vec![' '; (width + 3) * columns.len()]; height];
+        let mut rows: Vec<usize> = (0..columns[0].len()).map(|i| 2 * i).collect();
+
+        for (c, names) in columns.iter().enumerate() {
+            let x = c * (width + 3);
+            for (name, &y) in names.iter().zip(&rows) {
+                for (i, ch) in name.chars().enumerate() {
+                    grid[y][x + i] = ch;
+                }
+                if c + 1 < columns.len() {
+                    for cell in &mut grid[y][x + name.chars().count() + 1..x + width] {
+                        *cell = '─';
+                    }
+                }
+            }
+            if c + 1 == columns.len() {
+                break;
+            }
+
+            rows = rows
+                .chunks(2)
+                .map(|p| {
+                    let (top, bottom) = (p[0], p[1]);
+                    let middle = (top + bottom) / 2;
+                    grid[top][x + width] = '┐';
+                    grid[bottom][x + width] = '┘';
+                    for row in grid.iter_mut().take(bottom).skip(top + 1) {
+                        row[x + width] = '│';
+                    }
+                    grid[middle][x + width] = '├';
+                    grid[middle][x + width + 1] = '─';
+                    middle
+                })
+                .collect();
+        }
+
+        let mut out: Vec<String> = grid
+            .iter()
+            .map(|r| r.iter().collect::<String>().trim_end().to_owned())
+            .collect();
+
+        for m in 0..self.matches.len() {
+            if self.matches[m].side == Side::Winners {
+                continue;
+            }
+            // Matches decided without a game aren't shown
+            let (a, b) = match self.entrants(m) {
+                Some((_, _, true)) => continue,
+                Some((a, b, false)) => (Some(a), Some(b)),
+                None => (
+                    self.entrant(self.matches[m].slots[0]),
+                    self.entrant(self.matches[m].slots[1]),
+                ),
+            };
+            out.push(format!(
+                "{}: {} vs. {} → {}",
+                self.round_name(m),
+                Self::label(a),
+                Self::label(b),
+                Self::label(self.winner(m))
+            ));
+        }
+
+        out.join("\n")
+    }
+}
+
+/// Unlocked entries, best seed first
+fn seeds(rv: &RelVec, random: bool, ranking: Ranking) -> Vec<String> {
+    let mut order: Vec<usize> = (0..rv.len()).filter(|&i| !rv[i].locked).collect();
+    if random {
+        order.shuffle(&mut rand::thread_rng());
+    } else {
+        order.sort_by(|&a, &b| ranking.compare(&rv[a], &rv[b], &rv.config).reverse());
+    }
+
+    order.into_iter().map(|i| rv[i].name.clone()).collect()
+}
+
+/// Plays a single or double elimination bracket of all unlocked entries.
+/// When quitting, the bracket is saved in the list file and picked up by the
+/// next call.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn bracket(
+    input: &str,
+    output: Output,
+    double: bool,
+    random: bool,
+    restart: bool,
+    ranking: Ranking,
+    info: bool,
+    overrides: RatingOverrides,
+    voter: Option<String>,
+) -> Result<(), Error> {
+    let mut rv = RelVec::load(input)?;
+    overrides.apply(&mut rv.config);
+    rv.voter = voter;
+
+    let mut bracket = match rv.bracket.take() {
+        Some(bracket) if !restart && bracket.entrants_in(&rv) => {
+            println!("Resuming the previous bracket");
+            bracket
+        }
+        previous => {
+            if previous.is_some() && !restart {
+                println!("\x1b[33mEntries of the previous bracket are gone, starting over\x1b[0m");
+            }
+
+            let names = seeds(&rv, random, ranking);
+            if names.len() < 2 {
+                println!("There is no matching pair.");
+                return Ok(());
+            }
+            Bracket::new(names, double)
+        }
+    };
+    let mut played: Vec<usize> = Vec::new();
+    println!("{}", bracket.render());
+    println!("======================");
+
+    while let Some((m, a, b)) = bracket.next() {
+        let (i, j) = match (rv.position(a), rv.position(b)) {
+            (Some(i), Some(j)) => (i, j),
+            _ => return Err(Error::ArgError),
+        };
+
+        println!("\x1b[90m{}\x1b[0m", bracket.round_name(m));
+        show_pair(&rv, i, j, info);
+
+        let mut commands = vec![('b', "Show bracket".to_owned())];
+        if !played.is_empty() {
+            commands.push(('u', "Undo".to_owned()));
+        }
+        commands.push(('q', "Quit".to_owned()));
+
+        match read_command(&commands)? {
+            Some('1') => {
+                rv.decide(i, j, Outcome::First, "bracket");
+                bracket.set(m, Some(true));
+                played.push(m);
+            }
+            Some('2') => {
+                rv.decide(i, j, Outcome::Second, "bracket");
+                bracket.set(m, Some(false));
+                played.push(m);
+            }
+            Some('b') => println!("{}", bracket.render()),
+            Some('u') => match played.pop() {
+                Some(m) => {
+                    if let Some(record) = rv.undo() {
+                        println!("\x1b[33mUndone:\x1b[0m {}", record);
+                    }
+                    bracket.set(m, None);
+                }
+                None => println!("\x1b[31mnothing to undo\x1b[0m"),
+            },
+            // End of input quits as well
+            Some('q') | None => {
+                rv.bracket = Some(bracket);
+                rv.close_period();
+                return rv.save_to(output);
+            }
+            _ => println!("\x1b[31munknown command\x1b[0m"),
+        }
+
+        println!("======================");
+    }
+
+    println!("{}", bracket.render());
+    if let Some(champion) = bracket.champion() {
+        println!();
+        println!("Winner: \x1b[34m{}\x1b[0m", champion);
+    }
+
+    rv.close_period();
+    rv.save_to(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{seed_order, seeds, Bracket};
+    use crate::{history::Outcome, rating::Ranking, rel_vec::RelVec};
+
+    fn names(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// Plays every match, the name first in the alphabet wins
+    fn run(bracket: &mut Bracket) -> usize {
+        let mut games = 0;
+
+        while let Some((m, a, b)) = bracket.next() {
+            let first = a < b;
+            bracket.set(m, Some(first));
+            games += 1;
+        }
+
+        games
+    }
+
+    #[test]
+    fn bracket_seed_order() {
+        assert_eq!(seed_order(2), [0, 1]);
+        assert_eq!(seed_order(4), [0, 3, 1, 2]);
+        assert_eq!(seed_order(8), [0, 7, 3, 4, 1, 6, 2, 5]);
+    }
+
+    #[test]
+    fn bracket_single() {
+        let mut bracket = Bracket::new(names(&["a", "b", "c", "d", "e"]), false);
+
+        // Three byes for the best seeds
+        let (_, a, b) = bracket.next().unwrap();
+        assert_eq!((a, b), ("d", "e"));
+        assert_eq!(run(&mut bracket), 4);
+        assert_eq!(bracket.champion(), Some("a"));
+    }
+
+    #[test]
+    fn bracket_double() {
+        let mut bracket = Bracket::new(names(&["h", "g", "f", "e", "d", "c", "b", "a"]), true);
+
+        // The winner of the winners bracket wins the grand final, no reset
+        assert_eq!(run(&mut bracket), 14);
+        assert_eq!(bracket.champion(), Some("a"));
+    }
+
+    #[test]
+    fn bracket_double_reset() {
+        let mut bracket = Bracket::new(names(&["a", "b", "c", "d"]), true);
+        let mut games = 0;
+
+        // a loses only the first round, then wins every match
+        while let Some((m, x, y)) = bracket.next() {
+            let first = if games == 0 { x != "a" } else { x < y };
+            bracket.set(m, Some(first));
+            games += 1;
+        }
+
+        assert_eq!(games, 7);
+        assert_eq!(bracket.champion(), Some("a"));
+    }
+
+    #[test]
+    fn bracket_seeds() {
+        let mut rv = RelVec::create(names(&["a", "b", "c"]));
+        rv.decide(2, 1, Outcome::First, "random");
+        rv[0].locked = true;
+
+        assert_eq!(seeds(&rv, false, Ranking::Percentage), ["c", "b"]);
+        // The list keeps its order
+        assert_eq!(rv[2].name, "c");
+    }
+
+    #[test]
+    fn bracket_save_load() {
+        let mut bracket = Bracket::new(names(&["a", "b", "c", "d", "e"]), true);
+        let (m, _, _) = bracket.next().unwrap();
+        bracket.set(m, Some(false));
+
+        let json = serde_json::to_string(&bracket).unwrap();
+        let loaded: Bracket = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, bracket);
+    }
+
+    #[test]
+    fn bracket_render() {
+        let mut bracket = Bracket::new(names(&["a", "b", "c"]), false);
+        run(&mut bracket);
+
+        assert_eq!(
+            bracket.render(),
+            [
+                "a ─┐",
+                "   ├─ a ─┐",
+                "- ─┘     │",
+                "         ├─ a",
+                "b ─┐     │",
+                "   ├─ b ─┘",
+                "c ─┘",
+            ]
+            .join("\n")
+        );
+    }
+}
@@ -1,12 +1,74 @@
 use clap::{App, Arg, Shell, SubCommand};
 
-use crate::vote::VoteStrategy;
+use crate::{
+    condorcet::Method, history::RepeatPolicy, matrix::Format, rating::Ranking, storage::Backend,
+    vote::VoteStrategy,
+};
+
+/// `--by` option choosing the ranking, `help` says what it's used for
+fn by_arg(help: &'static str) -> Arg<'static, 'static> {
+    Arg::with_name("by")
+        .short("b")
+        .long("by")
+        .value_name("RANKING")
+        .help(help)
+        .takes_value(true)
+        .default_value("percentage")
+        .possible_values(&Ranking::rankings())
+}
+
+fn initial_elo_arg() -> Arg<'static, 'static> {
+    Arg::with_name("initialelo")
+        .long("initial-elo")
+        .value_name("RATING")
+        .help("Elo rating of unrated entries, defaults to the list's setting or 1500")
+        .takes_value(true)
+}
+
+fn tui_arg() -> Arg<'static, 'static> {
+    Arg::with_name("tui")
+        .short("t")
+        .long("tui")
+        .help("Use the full-screen prompt instead of reading commands line by line")
+}
+
+fn k_factor_arg() -> Arg<'static, 'static> {
+    Arg::with_name("kfactor")
+        .short("k")
+        .long("k-factor")
+        .value_name("K")
+        .help("Elo K-factor, defaults to the list's setting or 32")
+        .takes_value(true)
+}
 
 pub(crate) fn build_cli() -> App<'static, 'static> {
     App::new("relaty")
         .version("0.1.1")
         .about("Helps you sort and rate stuff")
         .author("Lichthagel <lichthagel@tuta.io>")
+        .arg(
+            Arg::with_name("wait")
+                .long("wait")
+                .help("Wait for other sessions to finish with the file instead of failing")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("backend")
+                .long("backend")
+                .value_name("BACKEND")
+                .help("Kind of list file to write, keeps the kind of an existing file and writes JSON otherwise if not given")
+                .takes_value(true)
+                .possible_values(&Backend::backends())
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("backups")
+                .long("backups")
+                .value_name("N")
+                .help("Number of backups kept of changed files, defaults to $RELATY_BACKUPS, else 3 for JSON files and none for databases")
+                .takes_value(true)
+                .global(true),
+        )
         .subcommand(
             SubCommand::with_name("new")
                 .about("Create an empty file")
@@ -29,6 +91,14 @@ pub(crate) fn build_cli() -> App<'static, 'static> {
                         .takes_value(true)
                         .multiple(true)
                         .index(2),
+                )
+                .arg(
+                    Arg::with_name("title")
+                        .short("t")
+                        .long("title")
+                        .value_name("TITLE")
+                        .help("Title of the list")
+                        .takes_value(true),
                 ),
         )
         .subcommand(
@@ -53,6 +123,14 @@ pub(crate) fn build_cli() -> App<'static, 'static> {
                         .required(true)
                         .takes_value(true)
                         .index(2),
+                )
+                .arg(
+                    Arg::with_name("title")
+                        .short("t")
+                        .long("title")
+                        .value_name("TITLE")
+                        .help("Title of the list")
+                        .takes_value(true),
                 ),
         )
         .subcommand(
@@ -93,6 +171,23 @@ pub(crate) fn build_cli() -> App<'static, 'static> {
                     Arg::with_name("nameonly")
                         .short("N")
                         .help("Only print the entry name"),
+                )
+                .arg(by_arg("Rank entries by"))
+                .arg(
+                    Arg::with_name("method")
+                        .long("method")
+                        .value_name("METHOD")
+                        .help("Rank entries by a pairwise voting method instead")
+                        .takes_value(true)
+                        .possible_values(&Method::methods()),
+                )
+                .arg(initial_elo_arg())
+                .arg(
+                    Arg::with_name("voter")
+                        .long("voter")
+                        .value_name("NAME")
+                        .help("Only count the decisions of this voter")
+                        .takes_value(true),
                 ),
         )
         .subcommand(
@@ -125,6 +220,134 @@ pub(crate) fn build_cli() -> App<'static, 'static> {
                         .index(2),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("place")
+                .about("Add an item and find its position with as few votes as possible")
+                .version("0.1.0")
+                .author("Lichthagel <lichthagel@tuta.io>")
+                .arg(
+                    Arg::with_name("file")
+                        .short("f")
+                        .value_name("FILE")
+                        .help("List file")
+                        .required(true)
+                        .takes_value(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("item")
+                        .value_name("ITEM")
+                        .help("Item to place")
+                        .required(true)
+                        .takes_value(true)
+                        .index(2),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .value_name("OUTPUT")
+                        .help("Output file")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("info")
+                        .short("i")
+                        .help("Shows additional information"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("sort")
+                .about("Sort all entries by merge sort, can be continued later")
+                .version("0.1.0")
+                .author("Lichthagel <lichthagel@tuta.io>")
+                .arg(
+                    Arg::with_name("file")
+                        .short("f")
+                        .value_name("FILE")
+                        .help("List file")
+                        .required(true)
+                        .takes_value(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .value_name("OUTPUT")
+                        .help("Output file")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("info")
+                        .short("i")
+                        .help("Shows additional information"),
+                )
+                .arg(
+                    Arg::with_name("restart")
+                        .long("restart")
+                        .help("Discard an unfinished sort and start over"),
+                )
+                .arg(tui_arg())
+                .arg(
+                    Arg::with_name("voter")
+                        .long("voter")
+                        .value_name("NAME")
+                        .help("Name decisions are recorded under, defaults to $RELATY_VOTER or the user name")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("bracket")
+                .about("Play a single or double elimination bracket")
+                .version("0.1.0")
+                .author("Lichthagel <lichthagel@tuta.io>")
+                .arg(
+                    Arg::with_name("file")
+                        .short("f")
+                        .value_name("FILE")
+                        .help("List file")
+                        .required(true)
+                        .takes_value(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .value_name("OUTPUT")
+                        .help("Output file")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("info")
+                        .short("i")
+                        .help("Shows additional information"),
+                )
+                .arg(
+                    Arg::with_name("double")
+                        .short("d")
+                        .long("double")
+                        .help("Double elimination, entries are out after two losses"),
+                )
+                .arg(
+                    Arg::with_name("random")
+                        .short("r")
+                        .long("random")
+                        .help("Seed entries randomly"),
+                )
+                .arg(
+                    Arg::with_name("restart")
+                        .long("restart")
+                        .help("Discard an unfinished bracket and start over"),
+                )
+                .arg(by_arg("Seed entries by"))
+                .arg(initial_elo_arg())
+                .arg(
+                    Arg::with_name("voter")
+                        .long("voter")
+                        .value_name("NAME")
+                        .help("Name decisions are recorded under, defaults to $RELATY_VOTER or the user name")
+                        .takes_value(true),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("remove")
                 .about("Remove elements from a list")
@@ -245,6 +468,88 @@ pub(crate) fn build_cli() -> App<'static, 'static> {
                         .index(2),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("fit")
+                .about("Fit Bradley–Terry strengths to the recorded votes")
+                .version("0.1.0")
+                .author("Lichthagel <lichthagel@tuta.io>")
+                .arg(
+                    Arg::with_name("file")
+                        .short("f")
+                        .value_name("FILE")
+                        .help("List file")
+                        .required(true)
+                        .takes_value(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .value_name("OUTPUT")
+                        .help("Output file")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("prior")
+                        .short("p")
+                        .long("prior")
+                        .value_name("PRIOR")
+                        .help("Virtual wins and losses of every entry against an average one, defaults to the list's setting or 1")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("undo")
+                .about("Revert the last recorded votes")
+                .version("0.1.0")
+                .author("Lichthagel <lichthagel@tuta.io>")
+                .arg(
+                    Arg::with_name("file")
+                        .short("f")
+                        .value_name("FILE")
+                        .help("List file")
+                        .required(true)
+                        .takes_value(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .value_name("OUTPUT")
+                        .help("Output file")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("count")
+                        .value_name("N")
+                        .help("Number of votes to revert")
+                        .takes_value(true)
+                        .index(2)
+                        .default_value("1"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("restore")
+                .about("Replace a list file by one of its backups")
+                .version("0.1.0")
+                .author("Lichthagel <lichthagel@tuta.io>")
+                .arg(
+                    Arg::with_name("file")
+                        .short("f")
+                        .value_name("FILE")
+                        .help("List file")
+                        .required(true)
+                        .takes_value(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("backup")
+                        .value_name("N")
+                        .help("Number of the backup, 1 is the newest. Lists the backups if missing.")
+                        .takes_value(true)
+                        .index(2),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("stats")
                 .about("Show stats about a list")
@@ -258,6 +563,122 @@ pub(crate) fn build_cli() -> App<'static, 'static> {
                         .required(true)
                         .takes_value(true)
                         .index(1),
+                )
+                .arg(by_arg("Rank entries by"))
+                .arg(initial_elo_arg())
+                .arg(
+                    Arg::with_name("voter")
+                        .long("voter")
+                        .value_name("NAME")
+                        .help("Only count the decisions of this voter")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("agreement")
+                .about("Compare the rankings of the voters of a list")
+                .version("0.1.0")
+                .author("Lichthagel <lichthagel@tuta.io>")
+                .arg(
+                    Arg::with_name("file")
+                        .short("f")
+                        .value_name("FILE")
+                        .help("List file")
+                        .required(true)
+                        .takes_value(true)
+                        .index(1),
+                )
+                .arg(by_arg("Rank entries by"))
+                .arg(initial_elo_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("cycles")
+                .about("Find entries that beat each other in cycles and vote on them again")
+                .version("0.1.0")
+                .author("Lichthagel <lichthagel@tuta.io>")
+                .arg(
+                    Arg::with_name("file")
+                        .short("f")
+                        .value_name("FILE")
+                        .help("List file")
+                        .required(true)
+                        .takes_value(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .value_name("OUTPUT")
+                        .help("Output file")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("info")
+                        .short("i")
+                        .help("Shows additional information"),
+                )
+                .arg(tui_arg())
+                .arg(
+                    Arg::with_name("voter")
+                        .long("voter")
+                        .value_name("NAME")
+                        .help("Name decisions are recorded under, defaults to $RELATY_VOTER or the user name")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("matrix")
+                .about("Print or export the wins of entries against each other")
+                .version("0.1.0")
+                .author("Lichthagel <lichthagel@tuta.io>")
+                .arg(
+                    Arg::with_name("file")
+                        .short("f")
+                        .value_name("FILE")
+                        .help("List file")
+                        .required(true)
+                        .takes_value(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .value_name("OUTPUT")
+                        .help("Output file")
+                        .takes_value(true)
+                        .index(2),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help("Output format, by default from the extension of the output file")
+                        .takes_value(true)
+                        .possible_values(&Format::formats()),
+                )
+                .arg(
+                    Arg::with_name("filter")
+                        .short("f")
+                        .value_name("filter")
+                        .help("Filter items by name")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("top")
+                        .short("k")
+                        .long("top")
+                        .value_name("K")
+                        .help("Only the best K entries")
+                        .takes_value(true),
+                )
+                .arg(by_arg("Rank entries by"))
+                .arg(initial_elo_arg())
+                .arg(
+                    Arg::with_name("voter")
+                        .long("voter")
+                        .value_name("NAME")
+                        .help("Only count the decisions of this voter")
+                        .takes_value(true),
                 ),
         )
         .subcommand(
@@ -299,10 +720,60 @@ pub(crate) fn build_cli() -> App<'static, 'static> {
                         .default_value("random")
                         .possible_values(&VoteStrategy::strategies()),
                 )
+                .arg(
+                    Arg::with_name("autosave")
+                        .long("autosave")
+                        .value_name("VOTES")
+                        .help("Votes between two autosaves to the journal, 0 disables it")
+                        .takes_value(true)
+                        .default_value("10"),
+                )
+                .arg(
+                    Arg::with_name("autosavetime")
+                        .long("autosave-time")
+                        .value_name("SECONDS")
+                        .help("Seconds between two autosaves to the journal, 0 only counts votes")
+                        .takes_value(true)
+                        .default_value("60"),
+                )
+                .arg(
+                    Arg::with_name("items")
+                        .short("n")
+                        .long("items")
+                        .value_name("K")
+                        .help("Number of entries shown at once, 2 to 9, only 2 with -s swiss")
+                        .takes_value(true)
+                        .default_value("2"),
+                )
+                .arg(tui_arg())
+                .arg(
+                    Arg::with_name("restart")
+                        .long("restart")
+                        .help("Discard the running Swiss-system tournament and start a new one"),
+                )
+                .arg(
+                    Arg::with_name("repeat")
+                        .long("repeat")
+                        .value_name("POLICY")
+                        .help("Whether pairs that were compared before are chosen again")
+                        .takes_value(true)
+                        .default_value("weighted")
+                        .possible_values(&RepeatPolicy::policies()),
+                )
                 .arg(
                     Arg::with_name("info")
                         .short("i")
                         .help("Shows additional information"),
+                )
+                .arg(by_arg("Rank entries by"))
+                .arg(initial_elo_arg())
+                .arg(k_factor_arg())
+                .arg(
+                    Arg::with_name("voter")
+                        .long("voter")
+                        .value_name("NAME")
+                        .help("Name decisions are recorded under, defaults to $RELATY_VOTER or the user name")
+                        .takes_value(true),
                 ),
         )
         .subcommand(
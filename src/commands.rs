@@ -6,33 +6,122 @@ use std::{
 use clap::Values;
 use regex::Regex;
 
-use crate::{error::Error, rel_vec::RelVec};
+use crate::{
+    condorcet::{condorcet_winner, cycle, overruled, Method},
+    error::Error,
+    history::date,
+    rating::{Ranking, RatingOverrides},
+    rel_vec::RelVec,
+    storage::Output,
+    swiss::Swiss,
+};
+
+pub(crate) fn new(output: Output, title: Option<&str>) -> Result<(), Error> {
+    let mut rv = RelVec::new();
+    rv.meta.title = title.map(str::to_owned);
+
+    rv.save_to(output)
+}
 
-pub(crate) fn new(output: &str) -> Result<(), Error> {
-    let rv = RelVec::new();
+pub(crate) fn create(output: Output, items: Vec<&str>, title: Option<&str>) -> Result<(), Error> {
+    let mut rv = RelVec::create(items.into_iter().map(|i| i.to_owned()).collect());
+    rv.meta.title = title.map(str::to_owned);
+    if let Some(name) = rv.duplicate() {
+        return Err(Error::Duplicate(name.to_owned()));
+    }
 
-    rv.save(output)
+    rv.save_to(output)
 }
 
-pub(crate) fn create(output: &str, items: Vec<&str>) -> Result<(), Error> {
-    let rv = RelVec::create(items.into_iter().map(|i| i.to_owned()).collect());
+pub(crate) fn from(input: &str, output: Output, title: Option<&str>) -> Result<(), Error> {
+    let mut rv = RelVec::from(input)?;
+    rv.meta.title = title.map(str::to_owned);
+    if let Some(name) = rv.duplicate() {
+        return Err(Error::Duplicate(name.to_owned()));
+    }
 
-    rv.save(output)
+    rv.save_to(output)
 }
 
-pub(crate) fn from(input: &str, output: &str) -> Result<(), Error> {
-    let rv = RelVec::from(input)?;
+/// Sorts the entries by a ranking or a pairwise voting method. A method only
+/// ranks the entries matching `filter` against each other, the others are
+/// dropped. Returns the description of each entry and, for a method, notes
+/// about the Condorcet winner and the cycles that were broken.
+fn sort_entries(
+    rv: &mut RelVec,
+    ranking: Ranking,
+    method: Option<Method>,
+    filter: &Regex,
+) -> (Vec<Option<String>>, Vec<String>) {
+    let method = match method {
+        Some(method) => method,
+        None => {
+            rv.sort_ranking(ranking);
+            let descriptions = rv.iter().map(|e| ranking.describe(e, &rv.config)).collect();
+            return (descriptions, Vec::new());
+        }
+    };
+
+    let entries: Vec<usize> = (0..rv.len())
+        .filter(|&i| filter.is_match(&rv[i].name))
+        .collect();
+    let matrix = rv.pairwise_of(&entries);
+    let aggregate = method.rank(&matrix);
+    let winner = condorcet_winner(&matrix);
+    let name = |i: usize| rv[entries[i]].name.clone();
+
+    let mut notes = vec![match winner {
+        Some(w) => format!("Condorcet winner: {}", name(w)),
+        None => "No Condorcet winner".to_owned(),
+    }];
+    for (w, l) in overruled(&matrix, &aggregate.order) {
+        match cycle(&matrix, w, l) {
+            Some(c) => notes.push(format!(
+                "Broken cycle: {}",
+                c.into_iter().map(name).collect::<Vec<_>>().join(" > ")
+            )),
+            None => notes.push(format!("Overruled: {} > {}", name(w), name(l))),
+        }
+    }
+
+    let descriptions = aggregate
+        .order
+        .iter()
+        .map(|&i| {
+            let mut d = method.describe(aggregate.scores[i]);
+            if winner == Some(i) {
+                d.push_str(", Condorcet winner");
+            }
+            Some(d)
+        })
+        .collect();
+    let sorted: Vec<_> = aggregate
+        .order
+        .iter()
+        .map(|&i| rv[entries[i]].clone())
+        .collect();
+    rv.clear();
+    rv.extend(sorted);
 
-    rv.save(output)
+    (descriptions, notes)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn print_screen(
     input: &str,
     filter: Option<&str>,
     linenumbers: bool,
     nameonly: bool,
+    ranking: Ranking,
+    method: Option<Method>,
+    voter: Option<&str>,
+    overrides: RatingOverrides,
 ) -> Result<(), Error> {
     let mut rv = RelVec::load(input)?;
+    overrides.apply(&mut rv.config);
+    if let Some(voter) = voter {
+        rv = rv.of_voter(voter);
+    }
     let re = match filter {
         Some(filter) => Regex::new(filter)?,
         None => Regex::new(".*?")?,
@@ -40,7 +129,7 @@ pub(crate) fn print_screen(
 
     let pad = rv.len().to_string().len(); // TODO improve
 
-    rv.sort_percentage();
+    let (descriptions, notes) = sort_entries(&mut rv, ranking, method, &re);
     for (i, e) in rv.iter().enumerate().filter(|(_, e)| re.is_match(&e.name)) {
         if linenumbers {
             let x = (i + 1).to_string();
@@ -48,22 +137,40 @@ pub(crate) fn print_screen(
         }
         if nameonly {
             println!("{}", e.name);
+        } else if let Some(d) = &descriptions[i] {
+            println!("{} - {}", e, d);
         } else {
-            println!("{}", e.to_string());
+            println!("{}", e);
+        }
+    }
+
+    if !nameonly && !notes.is_empty() {
+        println!();
+        for note in notes {
+            println!("{}", note);
         }
     }
 
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn print_file(
     input: &str,
     output: &str,
     filter: Option<&str>,
     linenumbers: bool,
     nameonly: bool,
+    ranking: Ranking,
+    method: Option<Method>,
+    voter: Option<&str>,
+    overrides: RatingOverrides,
 ) -> Result<(), Error> {
     let mut rv = RelVec::load(input)?;
+    overrides.apply(&mut rv.config);
+    if let Some(voter) = voter {
+        rv = rv.of_voter(voter);
+    }
     let output = File::create(output)?;
     let mut writer = BufWriter::new(output);
     let re = match filter {
@@ -73,7 +180,7 @@ pub(crate) fn print_file(
 
     let pad = rv.len().to_string().len(); // TODO improve
 
-    rv.sort_percentage();
+    let (descriptions, notes) = sort_entries(&mut rv, ranking, method, &re);
     for (i, e) in rv.iter().enumerate().filter(|(_, e)| re.is_match(&e.name)) {
         if linenumbers {
             let x = (i + 1).to_string();
@@ -85,29 +192,44 @@ pub(crate) fn print_file(
             writer.write_all(e.name.as_bytes())?;
         } else {
             writer.write_all(e.to_string().as_bytes())?;
+            if let Some(d) = &descriptions[i] {
+                writer.write_all(b" - ")?;
+                writer.write_all(d.as_bytes())?;
+            }
         }
         writer.write_all(b"\n")?;
     }
 
+    if !nameonly && !notes.is_empty() {
+        writer.write_all(b"\n")?;
+        for note in notes {
+            writer.write_all(note.as_bytes())?;
+            writer.write_all(b"\n")?;
+        }
+    }
+
     Ok(())
 }
 
-pub(crate) fn add(input: &str, output: &str, items: Values) -> Result<(), Error> {
+pub(crate) fn add(input: &str, output: Output, items: Values) -> Result<(), Error> {
     let mut rv = RelVec::load(input)?;
 
     for i in items {
+        if rv.position(i).is_some() {
+            return Err(Error::Duplicate(i.to_owned()));
+        }
         rv.add(i.to_owned());
     }
 
-    rv.save(output)
+    rv.save_to(output)
 }
 
-pub(crate) fn remove(input: &str, output: &str, filter: &str) -> Result<(), Error> {
+pub(crate) fn remove(input: &str, output: Output, filter: &str) -> Result<(), Error> {
     let mut rv = RelVec::load(input)?;
     let re = Regex::new(filter)?;
 
     for i in rv.iter().filter(|i| re.is_match(&i.name)) {
-        println!("{}", i.to_string());
+        println!("{}", i);
     }
 
     println!("\x1b[31mDo you want to remove these entries? [y/N]\x1b[0m");
@@ -116,30 +238,24 @@ pub(crate) fn remove(input: &str, output: &str, filter: &str) -> Result<(), Erro
     let _s = reader.read_line(&mut ans)?;
 
     match ans.chars().next() {
-        Some(c) => match c {
-            'y' | 'Y' => {
-                rv.remove(|i| re.is_match(&i.name));
+        Some('y') | Some('Y') => {
+            rv.remove(|i| re.is_match(&i.name));
 
-                rv.save(output)
-            }
-            _ => {
-                println!("Nothing resetted");
-                rv.save(output)
-            }
-        },
-        None => {
+            rv.save_to(output)
+        }
+        _ => {
             println!("Nothing resetted");
-            rv.save(output)
+            rv.save_to(output)
         }
     }
 }
 
-pub(crate) fn reset(input: &str, output: &str, filter: &str) -> Result<(), Error> {
+pub(crate) fn reset(input: &str, output: Output, filter: &str) -> Result<(), Error> {
     let mut rv = RelVec::load(input)?;
     let re = Regex::new(filter)?;
 
     for i in rv.iter().filter(|i| re.is_match(&i.name)) {
-        println!("{}", i.to_string());
+        println!("{}", i);
     }
 
     println!("\x1b[31mDo you want to reset these entries? [y/N]\x1b[0m");
@@ -148,32 +264,26 @@ pub(crate) fn reset(input: &str, output: &str, filter: &str) -> Result<(), Error
     let _s = reader.read_line(&mut ans)?;
 
     match ans.chars().next() {
-        Some(c) => match c {
-            'y' | 'Y' => {
-                rv.iter_mut()
-                    .filter(|i| re.is_match(&i.name))
-                    .for_each(|i| i.reset());
+        Some('y') | Some('Y') => {
+            rv.iter_mut()
+                .filter(|i| re.is_match(&i.name))
+                .for_each(|i| i.reset());
 
-                rv.save(output)
-            }
-            _ => {
-                println!("Nothing resetted");
-                rv.save(output)
-            }
-        },
-        None => {
+            rv.save_to(output)
+        }
+        _ => {
             println!("Nothing resetted");
-            rv.save(output)
+            rv.save_to(output)
         }
     }
 }
 
-pub(crate) fn lock(input: &str, output: &str, filter: &str, lock: bool) -> Result<(), Error> {
+pub(crate) fn lock(input: &str, output: Output, filter: &str, lock: bool) -> Result<(), Error> {
     let mut rv = RelVec::load(input)?;
     let re = Regex::new(filter)?;
 
     for i in rv.iter().filter(|i| re.is_match(&i.name)) {
-        println!("{}", i.to_string());
+        println!("{}", i);
     }
 
     println!(
@@ -185,29 +295,63 @@ pub(crate) fn lock(input: &str, output: &str, filter: &str, lock: bool) -> Resul
     let _s = reader.read_line(&mut ans)?;
 
     match ans.chars().next() {
-        Some(c) => match c {
-            'y' | 'Y' => {
-                rv.iter_mut()
-                    .filter(|i| re.is_match(&i.name))
-                    .for_each(|i| i.locked = lock);
+        Some('y') | Some('Y') => {
+            rv.iter_mut()
+                .filter(|i| re.is_match(&i.name))
+                .for_each(|i| i.locked = lock);
 
-                rv.save(output)
-            }
-            _ => {
-                println!("Nothing {}locked", if lock { "" } else { "un" });
-                rv.save(output)
-            }
-        },
-        None => {
+            rv.save_to(output)
+        }
+        _ => {
             println!("Nothing {}locked", if lock { "" } else { "un" });
-            rv.save(output)
+            rv.save_to(output)
+        }
+    }
+}
+
+pub(crate) fn fit(input: &str, output: Output, overrides: RatingOverrides) -> Result<(), Error> {
+    let mut rv = RelVec::load(input)?;
+    overrides.apply(&mut rv.config);
+
+    rv.fit_bradley_terry();
+    println!(
+        "Fitted \x1b[34m{}\x1b[0m entries to \x1b[34m{}\x1b[0m recorded votes",
+        rv.len(),
+        rv.history.len()
+    );
+
+    rv.save_to(output)
+}
+
+pub(crate) fn undo(input: &str, output: Output, count: usize) -> Result<(), Error> {
+    let mut rv = RelVec::load(input)?;
+
+    for _ in 0..count {
+        match rv.undo() {
+            Some(record) => println!("\x1b[33mUndone:\x1b[0m {}", record),
+            None => {
+                println!("Nothing left to undo");
+                break;
+            }
         }
     }
+
+    rv.save_to(output)
 }
 
-pub(crate) fn stats(input: &str) -> Result<(), Error> {
+pub(crate) fn stats(
+    input: &str,
+    ranking: Ranking,
+    voter: Option<&str>,
+    overrides: RatingOverrides,
+) -> Result<(), Error> {
     // TODO Use one loop
-    let rv = RelVec::load(input)?;
+    let mut rv = RelVec::load(input)?;
+    overrides.apply(&mut rv.config);
+    let voters = rv.voters();
+    if let Some(voter) = voter {
+        rv = rv.of_voter(voter);
+    }
 
     let min_p = rv
         .iter()
@@ -226,7 +370,14 @@ pub(crate) fn stats(input: &str) -> Result<(), Error> {
         .max_by(|a, b| a.votes.cmp(&b.votes))
         .map(|i| i.votes);
     let votes: u32 = rv.iter().map(|i| i.votes).sum();
+    let draws: u32 = rv.iter().map(|i| i.draws).sum();
 
+    if let Some(title) = &rv.meta.title {
+        println!("Title: \x1b[34m{}\x1b[0m", title);
+    }
+    if let Some(created) = rv.meta.created {
+        println!("Created: \x1b[34m{}\x1b[0m", date(created));
+    }
     println!("Number of entries: \x1b[34m{}\x1b[0m", rv.len());
     println!();
 
@@ -262,6 +413,11 @@ pub(crate) fn stats(input: &str) -> Result<(), Error> {
     }
     println!();
 
+    if ranking != Ranking::Percentage {
+        rating_stats(&rv, ranking);
+        println!();
+    }
+
     if let Some(min_v) = min_v {
         println!("Minimum votes: \x1b[34m{}\x1b[0m", min_v);
     }
@@ -286,6 +442,83 @@ pub(crate) fn stats(input: &str) -> Result<(), Error> {
     }
     println!();
     println!("Number of votes: \x1b[34m{}\x1b[0m (est.)", votes / 2);
+    println!("Number of draws: \x1b[34m{}\x1b[0m (est.)", draws / 2);
+
+    if let Some(swiss) = &rv.swiss {
+        swiss_stats(&rv, swiss);
+    }
+
+    if !voters.is_empty() {
+        println!();
+        for v in &voters {
+            let count = rv
+                .history
+                .iter()
+                .filter(|r| r.voter.as_ref() == Some(v) && r.score().is_some())
+                .count();
+            println!("Votes by {}: \x1b[34m{}\x1b[0m", v, count);
+        }
+    }
 
     Ok(())
 }
+
+fn swiss_stats(rv: &RelVec, swiss: &Swiss) {
+    let names: Vec<String> = rv.iter().map(|e| e.name.clone()).collect();
+
+    println!();
+    println!("Swiss round: \x1b[34m{}\x1b[0m", swiss.round);
+    for (i, s) in swiss.standings(&names).iter().enumerate() {
+        println!(
+            "\x1b[33m{:>3}.\x1b[0m {} - \x1b[34m{}\x1b[0m points in {} games \x1b[90m(Buchholz {})\x1b[0m",
+            i + 1,
+            s.name,
+            s.points,
+            s.games,
+            s.buchholz
+        );
+    }
+}
+
+fn rating_stats(rv: &RelVec, ranking: Ranking) {
+    let values: Vec<f64> = rv
+        .iter()
+        .map(|e| ranking.value(e, &rv.config))
+        .filter(|v| v.is_finite())
+        .collect();
+    let best = rv.iter().max_by(|a, b| ranking.compare(a, b, &rv.config));
+
+    if let (Some(best), false) = (best, values.is_empty()) {
+        println!("Best entry ({}): \x1b[34m{}\x1b[0m", ranking, best.name);
+    } else {
+        return;
+    }
+
+    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let width = (max - min) / 10.0;
+    let mut buckets = [0usize; 10];
+
+    println!("Minimum {}: \x1b[34m{:.1}\x1b[0m", ranking, min);
+    println!("Maximum {}: \x1b[34m{:.1}\x1b[0m", ranking, max);
+
+    for v in &values {
+        let i = if width > 0.0 {
+            (((v - min) / width) as usize).min(9)
+        } else {
+            0
+        };
+        buckets[i] += 1;
+    }
+    for (i, c) in buckets.iter().enumerate() {
+        let from = min + width * i as f64;
+        println!(
+            "\x1b[33m[{:>6.0},{:>6.0}{}\x1b[0m: \x1b[34m{}\x1b[0m \x1b[31m{}\x1b[0m",
+            from,
+            from + width,
+            if i == 9 { "]" } else { ")" },
+            "|".repeat(c / 5),
+            c
+        );
+    }
+}
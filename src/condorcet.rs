@@ -0,0 +1,443 @@
+use std::{collections::VecDeque, convert::TryFrom, fmt};
+
+use crate::error::Error;
+
+/// Pairwise voting method to rank entries by
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Method {
+    Schulze,
+    RankedPairs,
+    Copeland,
+    /// Local search for the order that keeps the most votes
+    Kemeny,
+}
+
+/// Result of a pairwise voting method
+#[derive(Clone, Debug, PartialEq)]
+pub struct Aggregate {
+    /// Indices of the entries, best first
+    pub order: Vec<usize>,
+    /// Score of each entry by index, see `Method::describe`
+    pub scores: Vec<f64>,
+}
+
+/// Whether `a` won more often against `b` than the other way around
+fn beats(matrix: &[Vec<f64>], a: usize, b: usize) -> bool {
+    matrix[a][b] > matrix[b][a]
+}
+
+/// Entries reachable from `from` in a graph given as adjacency lists. Stops
+/// early once `to` is reached.
+fn reachable(graph: &[Vec<usize>], from: usize, to: Option<usize>) -> Vec<bool> {
+    let mut seen = vec![false; graph.len()];
+    let mut stack = vec![from];
+    seen[from] = true;
+
+    while let Some(i) = stack.pop() {
+        for &j in &graph[i] {
+            if !seen[j] {
+                seen[j] = true;
+                if Some(j) == to {
+                    return seen;
+                }
+                stack.push(j);
+            }
+        }
+    }
+
+    seen
+}
+
+/// Entry that beats every other one head-to-head
+pub fn condorcet_winner(matrix: &[Vec<f64>]) -> Option<usize> {
+    (0..matrix.len()).find(|&a| (0..matrix.len()).all(|b| a == b || beats(matrix, a, b)))
+}
+
+/// Head-to-head results the order contradicts as (winner, loser). Each of
+/// them is part of a cycle that was broken or a tie that was resolved.
+pub fn overruled(matrix: &[Vec<f64>], order: &[usize]) -> Vec<(usize, usize)> {
+    let mut overruled = Vec::new();
+
+    for (n, &above) in order.iter().enumerate() {
+        for &below in &order[n + 1..] {
+            if beats(matrix, below, above) {
+                overruled.push((below, above));
+            }
+        }
+    }
+
+    overruled
+}
+
+/// Shortest cycle of head-to-head wins through the win of `a` against `b`,
+/// starting and ending with `a`
+pub fn cycle(matrix: &[Vec<f64>], a: usize, b: usize) -> Option<Vec<usize>> {
+    let mut previous = vec![None; matrix.len()];
+    let mut queue = VecDeque::from([b]);
+    previous[b] = Some(a);
+
+    while let Some(i) = queue.pop_front() {
+        if i == a {
+            let mut cycle = vec![a];
+            let mut j = previous[a]?;
+            while j != a {
+                cycle.push(j);
+                j = previous[j]?;
+            }
+            cycle.push(a);
+            cycle.reverse();
+            return Some(cycle);
+        }
+        for j in (0..matrix.len()).filter(|&j| beats(matrix, i, j)) {
+            if previous[j].is_none() {
+                previous[j] = Some(i);
+                queue.push_back(j);
+            }
+        }
+    }
+
+    None
+}
+
+/// Groups of entries connected by cycles of head-to-head wins, the strongly
+/// connected components with more than one entry
+pub fn components(matrix: &[Vec<f64>]) -> Vec<Vec<usize>> {
+    let n = matrix.len();
+    let graph: Vec<Vec<usize>> = (0..n)
+        .map(|a| (0..n).filter(|&b| beats(matrix, a, b)).collect())
+        .collect();
+    let reach: Vec<Vec<bool>> = (0..n).map(|a| reachable(&graph, a, None)).collect();
+
+    let mut seen = vec![false; n];
+    let mut components = Vec::new();
+    for a in 0..n {
+        if seen[a] {
+            continue;
+        }
+        let component: Vec<usize> = (0..n).filter(|&b| reach[a][b] && reach[b][a]).collect();
+        for &b in &component {
+            seen[b] = true;
+        }
+        if component.len() > 1 {
+            components.push(component);
+        }
+    }
+
+    components
+}
+
+/// Head-to-head wins as (winner, loser) within a component, they are all part
+/// of a cycle. The closest ones come first.
+pub fn conflicts(matrix: &[Vec<f64>], component: &[usize]) -> Vec<(usize, usize)> {
+    let mut conflicts: Vec<(usize, usize)> = component
+        .iter()
+        .flat_map(|&a| component.iter().map(move |&b| (a, b)))
+        .filter(|&(a, b)| beats(matrix, a, b))
+        .collect();
+    let margin = |(a, b): (usize, usize)| matrix[a][b] - matrix[b][a];
+    conflicts.sort_by(|&x, &y| margin(x).total_cmp(&margin(y)));
+
+    conflicts
+}
+
+/// Shortest cycle through each win of a component, without repetitions and
+/// the shortest ones first
+pub fn cycles(matrix: &[Vec<f64>], component: &[usize]) -> Vec<Vec<usize>> {
+    let mut cycles: Vec<Vec<usize>> = Vec::new();
+
+    for (a, b) in conflicts(matrix, component) {
+        if let Some(mut c) = cycle(matrix, a, b) {
+            // Start with the lowest index so that rotations are equal
+            c.pop();
+            let first = (0..c.len()).min_by_key(|&i| c[i]).unwrap_or(0);
+            c.rotate_left(first);
+            c.push(c[0]);
+            if !cycles.contains(&c) {
+                cycles.push(c);
+            }
+        }
+    }
+    cycles.sort_by_key(|c| c.len());
+
+    cycles
+}
+
+impl Method {
+    pub fn methods() -> [&'static str; 4] {
+        ["schulze", "ranked-pairs", "copeland", "kemeny-approx"]
+    }
+
+    /// Ranks entries by a matrix of their wins against each other
+    pub fn rank(&self, matrix: &[Vec<f64>]) -> Aggregate {
+        let scores = match self {
+            Method::Schulze => schulze(matrix),
+            Method::RankedPairs => ranked_pairs(matrix),
+            Method::Copeland | Method::Kemeny => copeland(matrix),
+        };
+
+        let mut order: Vec<usize> = (0..matrix.len()).collect();
+        order.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]));
+
+        if *self == Method::Kemeny {
+            kemeny(matrix, &mut order);
+            let mut overruled = vec![0.0; matrix.len()];
+            for (n, &above) in order.iter().enumerate() {
+                for &below in &order[n + 1..] {
+                    overruled[above] += matrix[below][above];
+                    overruled[below] += matrix[below][above];
+                }
+            }
+            return Aggregate {
+                order,
+                scores: overruled,
+            };
+        }
+
+        Aggregate { order, scores }
+    }
+
+    /// Score of an entry as text
+    pub fn describe(&self, score: f64) -> String {
+        let s = if score == 1.0 { "" } else { "s" };
+        match self {
+            Method::Schulze => format!("stronger than {}", score),
+            Method::RankedPairs => format!("locked above {}", score),
+            Method::Copeland => format!("{} point{}", score, s),
+            Method::Kemeny => format!("{} vote{} overruled", score, s),
+        }
+    }
+}
+
+/// One point for every head-to-head win, half a point for every tie
+fn copeland(matrix: &[Vec<f64>]) -> Vec<f64> {
+    (0..matrix.len())
+        .map(|a| {
+            (0..matrix.len())
+                .filter(|&b| a != b)
+                .map(|b| {
+                    if beats(matrix, a, b) {
+                        1.0
+                    } else if beats(matrix, b, a) {
+                        0.0
+                    } else {
+                        0.5
+                    }
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// Number of entries each one beats by the strongest paths of wins
+fn schulze(matrix: &[Vec<f64>]) -> Vec<f64> {
+    let n = matrix.len();
+    let mut strength = vec![vec![0.0; n]; n];
+
+    for a in 0..n {
+        for b in (0..n).filter(|&b| beats(matrix, a, b)) {
+            strength[a][b] = matrix[a][b];
+        }
+    }
+    for k in 0..n {
+        for a in (0..n).filter(|&a| a != k) {
+            for b in (0..n).filter(|&b| b != a && b != k) {
+                let path = f64::min(strength[a][k], strength[k][b]);
+                if path > strength[a][b] {
+                    strength[a][b] = path;
+                }
+            }
+        }
+    }
+
+    (0..n)
+        .map(|a| (0..n).filter(|&b| strength[a][b] > strength[b][a]).count() as f64)
+        .collect()
+}
+
+/// Number of entries each one is locked above. Wins are locked in from the
+/// largest margin down unless they would close a cycle.
+fn ranked_pairs(matrix: &[Vec<f64>]) -> Vec<f64> {
+    let n = matrix.len();
+    let margin = |a: usize, b: usize| matrix[a][b] - matrix[b][a];
+
+    let mut wins: Vec<(usize, usize)> = (0..n)
+        .flat_map(|a| (0..n).map(move |b| (a, b)))
+        .filter(|&(a, b)| beats(matrix, a, b))
+        .collect();
+    wins.sort_by(|&(a, b), &(c, d)| {
+        margin(c, d)
+            .total_cmp(&margin(a, b))
+            .then(matrix[c][d].total_cmp(&matrix[a][b]))
+    });
+
+    let mut locked: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for (a, b) in wins {
+        if !reachable(&locked, b, Some(a))[a] {
+            locked[a].push(b);
+        }
+    }
+
+    (0..n)
+        .map(|a| (reachable(&locked, a, None).iter().filter(|r| **r).count() - 1) as f64)
+        .collect()
+}
+
+/// Moves single entries to the position that keeps the most votes until no
+/// move improves the order
+fn kemeny(matrix: &[Vec<f64>], order: &mut Vec<usize>) {
+    let mut improved = true;
+
+    while improved {
+        improved = false;
+
+        for from in 0..order.len() {
+            let a = order[from];
+            let (mut best, mut to) = (0.0, from);
+
+            let mut gain = 0.0;
+            for i in (0..from).rev() {
+                let b = order[i];
+                gain += matrix[a][b] - matrix[b][a];
+                if gain > best {
+                    best = gain;
+                    to = i;
+                }
+            }
+            gain = 0.0;
+            for (i, &b) in order.iter().enumerate().skip(from + 1) {
+                gain += matrix[b][a] - matrix[a][b];
+                if gain > best {
+                    best = gain;
+                    to = i;
+                }
+            }
+
+            if to != from {
+                order.remove(from);
+                order.insert(to, a);
+                improved = true;
+            }
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Method::Schulze => write!(f, "schulze"),
+            Method::RankedPairs => write!(f, "ranked-pairs"),
+            Method::Copeland => write!(f, "copeland"),
+            Method::Kemeny => write!(f, "kemeny-approx"),
+        }
+    }
+}
+
+impl TryFrom<&str> for Method {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "schulze" => Ok(Method::Schulze),
+            "ranked-pairs" => Ok(Method::RankedPairs),
+            "copeland" => Ok(Method::Copeland),
+            "kemeny-approx" => Ok(Method::Kemeny),
+            _ => Err(Error::ArgError),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{components, condorcet_winner, conflicts, cycle, cycles, overruled, Method};
+
+    /// Wins of the row against the column
+    fn matrix(wins: &[&[u32]]) -> Vec<Vec<f64>> {
+        wins.iter()
+            .map(|r| r.iter().map(|w| f64::from(*w)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn condorcet_transitive() {
+        // a > b > c > d
+        let m = matrix(&[&[0, 3, 3, 4], &[1, 0, 3, 3], &[1, 1, 0, 3], &[0, 1, 1, 0]]);
+
+        assert_eq!(condorcet_winner(&m), Some(0));
+        for method in [
+            Method::Schulze,
+            Method::RankedPairs,
+            Method::Copeland,
+            Method::Kemeny,
+        ] {
+            let aggregate = method.rank(&m);
+            assert_eq!(aggregate.order, vec![0, 1, 2, 3], "{}", method);
+            assert!(overruled(&m, &aggregate.order).is_empty());
+        }
+    }
+
+    #[test]
+    fn condorcet_cycle() {
+        // a beats b 5-1, b beats c 4-2, c beats a 3-2, all beat d
+        let m = matrix(&[&[0, 5, 2, 3], &[1, 0, 4, 3], &[3, 2, 0, 3], &[0, 0, 0, 0]]);
+
+        assert_eq!(condorcet_winner(&m), None);
+        assert_eq!(cycle(&m, 2, 0), Some(vec![2, 0, 1, 2]));
+        assert_eq!(cycle(&m, 0, 3), None);
+
+        // The weakest win, of c against a, is dropped
+        let schulze = Method::Schulze.rank(&m);
+        assert_eq!(schulze.order, vec![0, 1, 2, 3]);
+        assert_eq!(schulze.scores, vec![3.0, 2.0, 1.0, 0.0]);
+        assert_eq!(overruled(&m, &schulze.order), vec![(2, 0)]);
+
+        let ranked_pairs = Method::RankedPairs.rank(&m);
+        assert_eq!(ranked_pairs.order, vec![0, 1, 2, 3]);
+        assert_eq!(ranked_pairs.scores, vec![3.0, 2.0, 1.0, 0.0]);
+
+        let kemeny = Method::Kemeny.rank(&m);
+        assert_eq!(overruled(&m, &kemeny.order).len(), 1);
+        assert_eq!(kemeny.order[3], 3);
+
+        // All three have one win against the cycle and one against d
+        assert_eq!(Method::Copeland.rank(&m).scores, vec![2.0, 2.0, 2.0, 0.0]);
+    }
+
+    #[test]
+    fn condorcet_components() {
+        // a > b > c > a, d > e > d is a tie, f beats all
+        let m = matrix(&[
+            &[0, 2, 0, 1, 1, 0],
+            &[0, 0, 2, 1, 1, 0],
+            &[1, 0, 0, 1, 1, 0],
+            &[0, 0, 0, 0, 1, 0],
+            &[0, 0, 0, 1, 0, 0],
+            &[1, 1, 1, 1, 1, 0],
+        ]);
+
+        assert_eq!(components(&m), vec![vec![0, 1, 2]]);
+        // c beat a by the smallest margin
+        assert_eq!(conflicts(&m, &[0, 1, 2]), vec![(2, 0), (0, 1), (1, 2)]);
+        assert_eq!(cycles(&m, &[0, 1, 2]), vec![vec![0, 1, 2, 0]]);
+    }
+
+    #[test]
+    fn condorcet_cycles() {
+        // a > b > c > a and a > b > d > a
+        let m = matrix(&[&[0, 1, 0, 0], &[0, 0, 1, 1], &[1, 0, 0, 0], &[1, 0, 0, 0]]);
+
+        assert_eq!(components(&m), vec![vec![0, 1, 2, 3]]);
+        assert_eq!(
+            cycles(&m, &[0, 1, 2, 3]),
+            vec![vec![0, 1, 2, 0], vec![0, 1, 3, 0]]
+        );
+    }
+
+    #[test]
+    fn condorcet_kemeny_scores() {
+        let m = matrix(&[&[0, 2], &[1, 0]]);
+        let kemeny = Method::Kemeny.rank(&m);
+
+        assert_eq!(kemeny.order, vec![0, 1]);
+        assert_eq!(kemeny.scores, vec![1.0, 1.0]);
+    }
+}
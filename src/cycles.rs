@@ -0,0 +1,126 @@
+use std::{collections::VecDeque, io, path::Path, time::Duration};
+
+use crate::{
+    condorcet::{components, conflicts, cycles as find_cycles},
+    error::Error,
+    journal::{Journal, AUTOSAVE_SECS, AUTOSAVE_VOTES},
+    rating::{Ranking, RatingOverrides},
+    rel_vec::RelVec,
+    storage::Output,
+    vote::{prompt, vote_pairs, ChooseFn},
+};
+
+/// Most cycles shown for each group of entries
+const MAX_CYCLES: usize = 10;
+
+/// Prints the groups of entries that beat each other in cycles and returns the
+/// conflicting pairs by name
+fn report(rv: &RelVec) -> Vec<(String, String)> {
+    let matrix = rv.pairwise();
+    let name = |i: usize| rv[i].name.as_str();
+    let mut pairs = Vec::new();
+
+    for (n, component) in components(&matrix).iter().enumerate() {
+        if n > 0 {
+            println!("======================");
+        }
+        println!(
+            "\x1b[34m{} entries\x1b[0m: {}",
+            component.len(),
+            component
+                .iter()
+                .map(|&i| name(i))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        let cycles = find_cycles(&matrix, component);
+        for c in cycles.iter().take(MAX_CYCLES) {
+            println!(
+                "{}",
+                c.iter().map(|&i| name(i)).collect::<Vec<_>>().join(" > ")
+            );
+        }
+        if cycles.len() > MAX_CYCLES {
+            println!("\x1b[90mand {} more\x1b[0m", cycles.len() - MAX_CYCLES);
+        }
+
+        pairs.extend(
+            conflicts(&matrix, component)
+                .into_iter()
+                .map(|(a, b)| (name(a).to_owned(), name(b).to_owned())),
+        );
+    }
+
+    pairs
+}
+
+/// Shows cycles of head-to-head wins and offers to vote on their pairs again
+pub(crate) fn cycles(
+    input: &str,
+    output: Output,
+    info: bool,
+    tui: bool,
+    overrides: RatingOverrides,
+    voter: Option<String>,
+) -> Result<(), Error> {
+    let mut rv = RelVec::load(input)?;
+    overrides.apply(&mut rv.config);
+    rv.voter = voter;
+    let mut journal = Journal::new(
+        Path::new(output.path),
+        &rv,
+        AUTOSAVE_VOTES,
+        Some(Duration::from_secs(AUTOSAVE_SECS)),
+    );
+    journal.recover(&mut rv)?;
+
+    let pairs = report(&rv);
+    if pairs.is_empty() {
+        println!("There are no cycles.");
+        return Ok(());
+    }
+
+    println!();
+    println!(
+        "\x1b[31mDo you want to vote on the {} conflicting pairs again? [y/N]\x1b[0m",
+        pairs.len()
+    );
+    let mut ans = String::new();
+    io::stdin().read_line(&mut ans)?;
+    if !matches!(ans.chars().next(), Some('y') | Some('Y')) {
+        return Ok(());
+    }
+
+    let rounds = pairs.len() as u32;
+    let mut queue: VecDeque<(String, String)> = pairs.into();
+    // Pairs with a removed entry are left out
+    let mut choose: ChooseFn = Box::new(move |rv: &mut RelVec| {
+        while let Some((a, b)) = queue.pop_front() {
+            if let (Some(a), Some(b)) = (rv.position(&a), rv.position(&b)) {
+                return Some((a, b));
+            }
+        }
+        None
+    });
+
+    let mut prompt = prompt(tui, input, Ranking::Percentage, info)?;
+    vote_pairs(
+        &mut rv,
+        prompt.as_mut(),
+        &mut choose,
+        &mut journal,
+        rounds,
+        "cycles",
+    )?;
+    drop(prompt);
+    rv.close_period();
+
+    println!();
+    if report(&rv).is_empty() {
+        println!("There are no cycles left.");
+    }
+
+    rv.save_to(output)?;
+    journal.finish()
+}
@@ -1,12 +1,30 @@
-use std::{io, num::ParseIntError};
+use std::{
+    fmt, io,
+    num::{ParseFloatError, ParseIntError},
+};
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     IoError(io::Error),
     Serde(serde_json::Error),
     Regex(regex::Error),
     Parse(ParseIntError),
+    ParseFloat(ParseFloatError),
     ArgError,
+    /// List file written by a newer version
+    Version(u32),
+    /// List file locked by another session, with its owner
+    Locked(String, String),
+    /// Entry name that is already in the list
+    Duplicate(String),
+    /// Argument with a value it can't have, and what it must be
+    OutOfRange(&'static str, String),
+    #[cfg(feature = "sqlite")]
+    Sqlite(rusqlite::Error),
+    /// SQLite file without the `sqlite` feature
+    #[cfg(not(feature = "sqlite"))]
+    NoSqlite,
 }
 
 impl From<io::Error> for Error {
@@ -21,6 +39,13 @@ impl From<serde_json::Error> for Error {
     }
 }
 
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Self {
+        Error::Sqlite(e)
+    }
+}
+
 impl From<regex::Error> for Error {
     fn from(e: regex::Error) -> Self {
         Error::Regex(e)
@@ -33,14 +58,41 @@ impl From<ParseIntError> for Error {
     }
 }
 
-impl ToString for Error {
-    fn to_string(&self) -> String {
+impl From<ParseFloatError> for Error {
+    fn from(e: ParseFloatError) -> Self {
+        Error::ParseFloat(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Error::IoError(e) => format!("IOError: {}", e.to_string()),
-            Error::Serde(e) => format!("Serialization Error: {}", e.to_string()),
-            Error::Regex(e) => format!("RegEx Error: {}", e.to_string()),
-            Error::Parse(e) => format!("Parse Error: {}", e.to_string()),
-            Error::ArgError => "argument is no UTF-8 string".to_string(),
+            Error::IoError(e) => write!(f, "IOError: {}", e),
+            Error::Serde(e) => write!(f, "Serialization Error: {}", e),
+            Error::Regex(e) => write!(f, "RegEx Error: {}", e),
+            Error::Parse(e) => write!(f, "Parse Error: {}", e),
+            Error::ParseFloat(e) => write!(f, "Parse Error: {}", e),
+            Error::ArgError => write!(f, "argument is no UTF-8 string"),
+            Error::Version(v) => write!(
+                f,
+                "list file has version {}, this version of relaty reads up to version {}",
+                v,
+                crate::rel_vec::VERSION
+            ),
+            Error::Locked(file, owner) => write!(
+                f,
+                "{} is used by another session ({}), try again later or use --wait",
+                file, owner
+            ),
+            Error::Duplicate(name) => write!(f, "\"{}\" is already in the list", name),
+            Error::OutOfRange(arg, range) => write!(f, "{} must be {}", arg, range),
+            #[cfg(feature = "sqlite")]
+            Error::Sqlite(e) => write!(f, "SQLite Error: {}", e),
+            #[cfg(not(feature = "sqlite"))]
+            Error::NoSqlite => write!(
+                f,
+                "this build of relaty can't read SQLite files, build it with `--features sqlite`"
+            ),
         }
     }
 }
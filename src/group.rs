@@ -0,0 +1,185 @@
+use rand::prelude::SliceRandom;
+
+use crate::{
+    error::Error,
+    history::Outcome,
+    journal::Journal,
+    rel_vec::RelVec,
+    vote::{ChooseFn, Prompt},
+};
+
+/// Most entries shown at once, each needs its own number key
+pub const MAX_ITEMS: usize = 9;
+
+/// Picks `k` entries, as many as possible by the strategy and the rest at random
+fn pick(rv: &mut RelVec, choose: &mut ChooseFn, k: usize) -> Option<Vec<usize>> {
+    let (a, b) = choose(rv)?;
+    let mut group = vec![a, b];
+
+    for _ in 0..k * 2 {
+        if group.len() >= k {
+            break;
+        }
+        match choose(rv) {
+            Some((c, d)) => {
+                for i in [c, d] {
+                    if group.len() < k && !group.contains(&i) {
+                        group.push(i);
+                    }
+                }
+            }
+            None => break,
+        }
+    }
+
+    let mut rest: Vec<usize> = rv
+        .reduced()
+        .into_iter()
+        .filter(|i| !group.contains(i))
+        .collect();
+    rest.shuffle(&mut rand::thread_rng());
+    let missing = k.saturating_sub(group.len());
+    group.extend(rest.into_iter().take(missing));
+    group.shuffle(&mut rand::thread_rng());
+
+    Some(group)
+}
+
+/// Positions (starting at 0) in the order of an answer like `3` or `3 1 4 2`.
+/// If only one entry is missing it is added as the last one.
+pub fn parse_ranking(answer: &str, k: usize) -> Option<Vec<usize>> {
+    let mut order = Vec::new();
+
+    for c in answer.chars().filter(|c| !c.is_whitespace() && *c != ',') {
+        let i = c.to_digit(10)? as usize;
+        if i < 1 || i > k || order.contains(&(i - 1)) {
+            return None;
+        }
+        order.push(i - 1);
+    }
+
+    if order.len() + 1 == k {
+        order.extend((0..k).find(|i| !order.contains(i)));
+    }
+    if order.is_empty() {
+        None
+    } else {
+        Some(order)
+    }
+}
+
+/// Pairwise results of a partial ranking of `k` entries as (winner, loser).
+/// Every ranked entry beats the ones ranked below it and all unranked ones.
+pub fn outcomes(order: &[usize], k: usize) -> Vec<(usize, usize)> {
+    let unranked: Vec<usize> = (0..k).filter(|i| !order.contains(i)).collect();
+
+    order
+        .iter()
+        .enumerate()
+        .flat_map(|(n, &w)| order[n + 1..].iter().chain(&unranked).map(move |&l| (w, l)))
+        .collect()
+}
+
+/// Votes between `k` entries at once: the answer is the best one or a
+/// ranking, recorded as pairwise decisions. Returns `false` if there was no
+/// group to vote on.
+pub(crate) fn vote_group(
+    rv: &mut RelVec,
+    prompt: &mut dyn Prompt,
+    choose: &mut ChooseFn,
+    journal: &mut Journal,
+    rounds: u32,
+    k: usize,
+    strategy: &str,
+) -> Result<bool, Error> {
+    // Number of decisions of each answer of this session, to undo them together
+    let mut answers: Vec<usize> = Vec::new();
+    let mut next = None;
+    let mut round = 0;
+
+    while round < rounds {
+        journal.update(rv)?;
+        let group = match next.take().or_else(|| pick(rv, choose, k)) {
+            Some(group) => group,
+            None => return Ok(false),
+        };
+
+        let mut commands = vec![('o', "Can't decide".to_owned())];
+        if !answers.is_empty() {
+            commands.push(('u', "Undo".to_owned()));
+        }
+        commands.push(('q', "Quit".to_owned()));
+
+        let answer = match prompt.ask_group(rv, &group, &commands, (round, rounds))? {
+            Some(answer) => answer,
+            None => return Ok(true),
+        };
+
+        match answer.as_str() {
+            "o" => {}
+            "u" => {
+                match answers.pop() {
+                    Some(n) => {
+                        for _ in 0..n {
+                            rv.undo();
+                        }
+                        prompt.message("\x1b[33mUndone\x1b[0m");
+                        round = round.saturating_sub(1);
+                    }
+                    None => {
+                        prompt.message("\x1b[31mnothing to undo\x1b[0m");
+                        next = Some(group);
+                    }
+                }
+                continue;
+            }
+            "q" => return Ok(true),
+            _ => match parse_ranking(&answer, group.len()) {
+                Some(order) => {
+                    let decisions = outcomes(&order, group.len());
+                    for (w, l) in &decisions {
+                        rv.decide(group[*w], group[*l], Outcome::First, strategy);
+                    }
+                    answers.push(decisions.len());
+                }
+                None => {
+                    prompt.message("\x1b[31munknown command\x1b[0m");
+                    next = Some(group);
+                    continue;
+                }
+            },
+        }
+
+        round += 1;
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{outcomes, parse_ranking};
+
+    #[test]
+    fn group_parse_ranking() {
+        assert_eq!(parse_ranking("3", 4), Some(vec![2]));
+        assert_eq!(parse_ranking("3 1 4 2", 4), Some(vec![2, 0, 3, 1]));
+        assert_eq!(parse_ranking("3,1", 3), Some(vec![2, 0, 1]));
+        assert_eq!(parse_ranking("314", 4), Some(vec![2, 0, 3, 1]));
+        assert_eq!(parse_ranking("5", 4), None);
+        assert_eq!(parse_ranking("0", 4), None);
+        assert_eq!(parse_ranking("11", 4), None);
+        assert_eq!(parse_ranking("x", 4), None);
+        assert_eq!(parse_ranking("", 4), None);
+    }
+
+    #[test]
+    fn group_outcomes() {
+        assert_eq!(outcomes(&[1], 3), vec![(1, 0), (1, 2)]);
+        assert_eq!(outcomes(&[2, 0, 1], 3), vec![(2, 0), (2, 1), (0, 1)]);
+        assert_eq!(
+            outcomes(&[3, 0], 4),
+            vec![(3, 0), (3, 1), (3, 2), (0, 1), (0, 2)]
+        );
+    }
+}
@@ -0,0 +1,533 @@
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{error::Error, rating::Glicko, rel_vec::RelEntry};
+
+/// Result of a decision between two entries
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Outcome {
+    /// The first entry won
+    #[serde(rename = "1")]
+    First,
+    /// The second entry won
+    #[serde(rename = "2")]
+    Second,
+    /// Both entries are equally good
+    #[serde(rename = "=")]
+    Draw,
+    /// No decision was made
+    #[serde(rename = "o")]
+    Skip,
+    /// The first entry was removed from the list
+    #[serde(rename = "x")]
+    RemoveFirst,
+    /// The second entry was removed from the list
+    #[serde(rename = "y")]
+    RemoveSecond,
+}
+
+impl Outcome {
+    /// Score of the first entry (1 = win, 0.5 = draw, 0 = loss), if it was compared
+    pub fn score(&self) -> Option<f64> {
+        match self {
+            Outcome::First => Some(1.0),
+            Outcome::Second => Some(0.0),
+            Outcome::Draw => Some(0.5),
+            _ => None,
+        }
+    }
+}
+
+/// A single recorded decision
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub a: String,
+    pub b: String,
+    #[serde(rename = "o")]
+    pub outcome: Outcome,
+    /// Seconds since the unix epoch, 0 if unknown
+    #[serde(rename = "t", default)]
+    pub time: u64,
+    /// Strategy that chose the pair
+    #[serde(rename = "s", default)]
+    pub strategy: String,
+    /// Ratings of both entries before the decision, `None` if both were
+    /// unrated
+    #[serde(rename = "p", default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<[Snapshot; 2]>,
+    /// Position and state of a removed entry, only the position is stored if
+    /// it was never voted on
+    #[serde(
+        rename = "r",
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "removed"
+    )]
+    pub removed: Option<(usize, RelEntry)>,
+    /// Weight of the decision in the rating models, `CLEAR` if the winner
+    /// was clearly better
+    #[serde(rename = "m", default = "normal", skip_serializing_if = "is_normal")]
+    pub margin: u32,
+    /// Who made the decision
+    #[serde(rename = "u", default, skip_serializing_if = "Option::is_none")]
+    pub voter: Option<String>,
+}
+
+/// Margin of a clear decision
+pub const CLEAR: u32 = 2;
+
+fn normal() -> u32 {
+    1
+}
+
+fn is_normal(margin: &u32) -> bool {
+    *margin == 1
+}
+
+impl Record {
+    pub fn new(a: String, b: String, outcome: Outcome, strategy: String) -> Self {
+        Self {
+            a,
+            b,
+            outcome,
+            time: now(),
+            strategy,
+            previous: None,
+            removed: None,
+            margin: 1,
+            voter: None,
+        }
+    }
+
+    /// Score of the first entry (1 = win, 0.5 = draw, 0 = loss), if it was compared
+    pub fn score(&self) -> Option<f64> {
+        self.outcome.score()
+    }
+
+    /// Names of the compared pair, `None` for skips and removals
+    pub fn compared(&self) -> Option<(&str, &str)> {
+        self.score().map(|_| (self.a.as_str(), self.b.as_str()))
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.outcome {
+            Outcome::First if self.margin > 1 => {
+                write!(f, "{} clearly won against {}", self.a, self.b)
+            }
+            Outcome::Second if self.margin > 1 => {
+                write!(f, "{} clearly won against {}", self.b, self.a)
+            }
+            Outcome::First => write!(f, "{} won against {}", self.a, self.b),
+            Outcome::Second => write!(f, "{} won against {}", self.b, self.a),
+            Outcome::Draw => write!(f, "{} vs. {} was a draw", self.a, self.b),
+            Outcome::Skip => write!(f, "{} vs. {} skipped", self.a, self.b),
+            Outcome::RemoveFirst => write!(f, "{} removed", self.a),
+            Outcome::RemoveSecond => write!(f, "{} removed", self.b),
+        }
+    }
+}
+
+/// Ratings of an entry at some point, used to undo decisions
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    #[serde(rename = "e", default, skip_serializing_if = "Option::is_none")]
+    pub elo: Option<f64>,
+    #[serde(rename = "g", default, skip_serializing_if = "Option::is_none")]
+    pub glicko: Option<Glicko>,
+}
+
+impl Snapshot {
+    pub fn of(entry: &RelEntry) -> Self {
+        Self {
+            elo: entry.elo,
+            glicko: entry.glicko,
+        }
+    }
+
+    pub fn restore(&self, entry: &mut RelEntry) {
+        entry.elo = self.elo;
+        entry.glicko = self.glicko;
+    }
+
+    /// Whether the entry was unrated
+    pub fn is_empty(&self) -> bool {
+        self.elo.is_none() && self.glicko.is_none()
+    }
+}
+
+/// Removed entries as stored in the history. The name of an entry stored by
+/// its position is left empty, it's the name in the record.
+mod removed {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::rel_vec::RelEntry;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum Removed {
+        Position(usize),
+        Entry(usize, RelEntry),
+    }
+
+    pub fn serialize<S: Serializer>(
+        removed: &Option<(usize, RelEntry)>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match removed {
+            Some((i, e)) if e.is_new() => Removed::Position(*i).serialize(serializer),
+            Some((i, e)) => Removed::Entry(*i, e.clone()).serialize(serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<(usize, RelEntry)>, D::Error> {
+        Ok(match Removed::deserialize(deserializer)? {
+            Removed::Position(i) => Some((i, RelEntry::new(String::new(), 0, 0))),
+            Removed::Entry(i, e) => Some((i, e)),
+        })
+    }
+}
+
+/// Whether pairs that were compared before may be chosen again
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RepeatPolicy {
+    /// Less often the more often and the more recently they were compared
+    #[default]
+    Weighted,
+    /// Only once all other pairs were compared as often
+    Exhaust,
+    /// Never
+    Never,
+    /// Always, the history is ignored
+    Allow,
+}
+
+impl RepeatPolicy {
+    pub fn policies() -> [&'static str; 4] {
+        ["weighted", "exhaust", "never", "allow"]
+    }
+}
+
+impl fmt::Display for RepeatPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepeatPolicy::Weighted => write!(f, "weighted"),
+            RepeatPolicy::Exhaust => write!(f, "exhaust"),
+            RepeatPolicy::Never => write!(f, "never"),
+            RepeatPolicy::Allow => write!(f, "allow"),
+        }
+    }
+}
+
+impl TryFrom<&str> for RepeatPolicy {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "weighted" => Ok(RepeatPolicy::Weighted),
+            "exhaust" => Ok(RepeatPolicy::Exhaust),
+            "never" => Ok(RepeatPolicy::Never),
+            "allow" => Ok(RepeatPolicy::Allow),
+            _ => Err(Error::ArgError),
+        }
+    }
+}
+
+/// How often and how recently pairs were compared, turned into a weight
+/// between 0 (must not be chosen) and 1 (no restriction) by a `RepeatPolicy`.
+/// Built once and updated with every new decision.
+#[derive(Clone, Debug)]
+pub struct PairWeights {
+    policy: RepeatPolicy,
+    /// Numbers of the names, so pairs are keyed without copying names
+    ids: HashMap<String, usize>,
+    /// Number of comparisons and index of the last one by pair of ids
+    pairs: HashMap<(usize, usize), (u32, usize)>,
+    len: usize,
+    /// Ids of the entries whose pairs `Exhaust` compares
+    names: HashSet<usize>,
+    /// Least number of comparisons of any pair of `names` and how many
+    /// pairs were compared that often
+    min: (u32, usize),
+    /// Number of decisions after which a weighted pair is half as likely
+    half_life: f64,
+}
+
+impl PairWeights {
+    /// Weights for the pairs of the given entries
+    pub fn new(history: &[Record], names: &[&str], policy: RepeatPolicy) -> Self {
+        Self::of_pairs(history.iter().map(Record::compared), names, policy)
+    }
+
+    /// Weights for pairs compared in the given order, `None` for steps that
+    /// compared nothing
+    pub fn of_pairs<'a, I>(compared: I, names: &[&str], policy: RepeatPolicy) -> Self
+    where
+        I: IntoIterator<Item = Option<(&'a str, &'a str)>>,
+    {
+        let mut weights = Self {
+            policy,
+            ids: HashMap::new(),
+            pairs: HashMap::new(),
+            len: 0,
+            names: HashSet::new(),
+            min: (0, 0),
+            half_life: names.len().max(1) as f64,
+        };
+        weights.names = names.iter().map(|n| weights.id(n)).collect();
+
+        for pair in compared {
+            weights.count(pair);
+        }
+        weights.min = weights.least();
+
+        weights
+    }
+
+    /// Whether the weights were built for `len` decisions between `names`
+    /// entries with the given policy
+    pub fn is_current(&self, len: usize, names: usize, policy: RepeatPolicy) -> bool {
+        self.len == len && self.names.len() == names && self.policy == policy
+    }
+
+    fn id(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+
+        let id = self.ids.len();
+        self.ids.insert(name.to_owned(), id);
+        id
+    }
+
+    /// Number of a name for `weight_of`
+    pub fn id_of(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).copied()
+    }
+
+    /// Counts a comparison, returns its key and the previous count
+    fn count(&mut self, pair: Option<(&str, &str)>) -> Option<((usize, usize), u32)> {
+        let i = self.len;
+        self.len += 1;
+
+        let (a, b) = pair.filter(|_| self.policy != RepeatPolicy::Allow)?;
+        let (a, b) = (self.id(a), self.id(b));
+        let key = (a.min(b), a.max(b));
+        let e = self.pairs.entry(key).or_insert((0, 0));
+        let previous = e.0;
+        e.0 += 1;
+        e.1 = i;
+
+        Some((key, previous))
+    }
+
+    /// Adds the next decision, `None` if it compared nothing
+    pub fn push(&mut self, pair: Option<(&str, &str)>) {
+        let (key, previous) = match self.count(pair) {
+            Some(counted) => counted,
+            None => return,
+        };
+
+        if self.policy == RepeatPolicy::Exhaust
+            && key.0 != key.1
+            && self.names.contains(&key.0)
+            && self.names.contains(&key.1)
+            && previous == self.min.0
+        {
+            self.min.1 -= 1;
+            if self.min.1 == 0 {
+                self.min = self.least();
+            }
+        }
+    }
+
+    /// Least number of comparisons of any pair of `names` and how many pairs
+    /// were compared that often
+    fn least(&self) -> (u32, usize) {
+        if self.policy != RepeatPolicy::Exhaust {
+            return (0, 0);
+        }
+
+        let mut counts: HashMap<u32, usize> = HashMap::new();
+        for (&(a, b), &(c, _)) in &self.pairs {
+            if a != b && self.names.contains(&a) && self.names.contains(&b) {
+                *counts.entry(c).or_insert(0) += 1;
+            }
+        }
+
+        let n = self.names.len();
+        let unseen = n * n.saturating_sub(1) / 2 - counts.values().sum::<usize>();
+        if unseen > 0 {
+            (0, unseen)
+        } else {
+            counts.into_iter().min().unwrap_or((0, 0))
+        }
+    }
+
+    pub fn weight(&self, a: &str, b: &str) -> f64 {
+        self.weight_of(self.id_of(a), self.id_of(b))
+    }
+
+    /// Like `weight` for the numbers `id_of` returns
+    pub fn weight_of(&self, a: Option<usize>, b: Option<usize>) -> f64 {
+        let key = match (a, b) {
+            (Some(a), Some(b)) => (a.min(b), a.max(b)),
+            _ => return 1.0,
+        };
+        let (count, last) = match self.pairs.get(&key) {
+            Some(&(count, last)) => (count, last),
+            None => return 1.0,
+        };
+
+        match self.policy {
+            RepeatPolicy::Allow => 1.0,
+            RepeatPolicy::Never => 0.0,
+            RepeatPolicy::Exhaust => {
+                if count <= self.min.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            RepeatPolicy::Weighted => {
+                // Recovers towards 1 the longer ago the last comparison was,
+                // slower the more often the pair was compared
+                let age = (self.len - last) as f64;
+                age / (age + self.half_life * f64::from(count))
+            }
+        }
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// UTC date of a Unix time as `YYYY-MM-DD`
+pub fn date(time: u64) -> String {
+    // Days to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = (time / 86400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// UTC date and time of a Unix time as `YYYY-MM-DD hh:mm:ss`
+pub fn datetime(time: u64) -> String {
+    let seconds = time % 86400;
+
+    format!(
+        "{} {:02}:{:02}:{:02}",
+        date(time),
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{date, datetime, Outcome, PairWeights, Record, RepeatPolicy};
+
+    fn history(pairs: &[(&str, &str)]) -> Vec<Record> {
+        pairs
+            .iter()
+            .map(|(a, b)| Record::new(a.to_string(), b.to_string(), Outcome::First, String::new()))
+            .collect()
+    }
+
+    #[test]
+    fn pair_weights_never() {
+        let h = history(&[("a", "b")]);
+        let w = PairWeights::new(&h, &["a", "b", "c"], RepeatPolicy::Never);
+
+        assert_eq!(w.weight("a", "b"), 0.0);
+        assert_eq!(w.weight("b", "a"), 0.0);
+        assert_eq!(w.weight("a", "c"), 1.0);
+
+        // Skipped pairs weren't compared
+        let mut h = history(&[("a", "b")]);
+        h[0].outcome = Outcome::Skip;
+        let w = PairWeights::new(&h, &["a", "b", "c"], RepeatPolicy::Never);
+
+        assert_eq!(w.weight("a", "b"), 1.0);
+    }
+
+    #[test]
+    fn pair_weights_exhaust() {
+        let h = history(&[("a", "b"), ("a", "c")]);
+        let w = PairWeights::new(&h, &["a", "b", "c"], RepeatPolicy::Exhaust);
+
+        assert_eq!(w.weight("a", "b"), 0.0);
+        assert_eq!(w.weight("b", "c"), 1.0);
+
+        let h = history(&[("a", "b"), ("a", "c"), ("c", "b"), ("a", "b")]);
+        let w = PairWeights::new(&h, &["a", "b", "c"], RepeatPolicy::Exhaust);
+
+        assert_eq!(w.weight("a", "b"), 0.0);
+        assert_eq!(w.weight("a", "c"), 1.0);
+        assert_eq!(w.weight("b", "c"), 1.0);
+    }
+
+    #[test]
+    fn pair_weights_weighted() {
+        let h = history(&[("a", "b"), ("a", "c"), ("a", "c"), ("b", "c")]);
+        let w = PairWeights::new(&h, &["a", "b", "c"], RepeatPolicy::Weighted);
+
+        assert_eq!(w.weight("a", "d"), 1.0);
+        assert!(w.weight("b", "c") < w.weight("a", "b"));
+        assert!(w.weight("a", "c") < w.weight("a", "b"));
+        assert!(w.weight("b", "c") > 0.0);
+
+        // A pair compared once becomes likely again
+        let mut h = history(&[("a", "b")]);
+        h.extend(history(&[("a", "c"); 30]));
+        let w = PairWeights::new(&h, &["a", "b", "c"], RepeatPolicy::Weighted);
+        assert!(w.weight("a", "b") > 0.9);
+    }
+
+    #[test]
+    fn pair_weights_push() {
+        let names = ["a", "b", "c"];
+        let h = history(&[("a", "b"), ("a", "c"), ("c", "b"), ("a", "b")]);
+
+        for policy in [RepeatPolicy::Exhaust, RepeatPolicy::Weighted] {
+            let mut w = PairWeights::new(&h[..1], &names, policy);
+            for r in &h[1..] {
+                w.push(Some((&r.a, &r.b)));
+            }
+            let built = PairWeights::new(&h, &names, policy);
+
+            assert!(w.is_current(h.len(), names.len(), policy));
+            for (a, b) in [("a", "b"), ("a", "c"), ("b", "c")] {
+                assert_eq!(w.weight(a, b), built.weight(a, b));
+            }
+        }
+    }
+
+    #[test]
+    fn history_date() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(951_782_400), "2000-02-29");
+        assert_eq!(date(1_600_000_000), "2020-09-13");
+        assert_eq!(datetime(1_600_000_000), "2020-09-13 12:26:40");
+    }
+}
@@ -0,0 +1,282 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crossterm::tty::IsTty;
+
+use crate::{
+    backup::{self, sibling},
+    error::Error,
+    history::{datetime, Record},
+    rel_vec::RelVec,
+};
+
+/// Votes between two autosaves unless set on the command line
+pub const AUTOSAVE_VOTES: usize = 10;
+/// Seconds between two autosaves unless set on the command line
+pub const AUTOSAVE_SECS: u64 = 60;
+
+/// Journal as written to `<FILE>.journal`
+#[derive(Serialize)]
+struct JournalRef<'a> {
+    /// Length of the history when the session started
+    base: usize,
+    records: &'a [Record],
+}
+
+#[derive(Deserialize)]
+struct JournalFile {
+    base: usize,
+    records: Vec<Record>,
+}
+
+/// Votes of a running session that are not saved to the list yet. They are
+/// written to `<FILE>.journal` every few votes or seconds, and the journal is
+/// removed once the list is saved.
+pub struct Journal {
+    path: PathBuf,
+    base: usize,
+    /// Votes between two writes, 0 disables the journal
+    votes: usize,
+    /// Time between two writes, if it counts
+    interval: Option<Duration>,
+    seen: usize,
+    unsaved: usize,
+    written: Instant,
+}
+
+impl Journal {
+    /// Journal of a session on `rv` that saves to `list`
+    pub fn new(list: &Path, rv: &RelVec, votes: usize, interval: Option<Duration>) -> Self {
+        Self {
+            path: sibling(list, ".journal"),
+            base: rv.history.len(),
+            votes,
+            interval,
+            seen: rv.history.len(),
+            unsaved: 0,
+            written: Instant::now(),
+        }
+    }
+
+    /// Notes changes of the history and writes the journal if it's due
+    pub fn update(&mut self, rv: &RelVec) -> Result<(), Error> {
+        if self.votes == 0 {
+            return Ok(());
+        }
+        if rv.history.len() != self.seen {
+            self.seen = rv.history.len();
+            self.unsaved += 1;
+        }
+
+        let due = self.unsaved >= self.votes
+            || self.interval.is_some_and(|i| self.written.elapsed() >= i);
+        if self.unsaved > 0 && due {
+            self.write(rv)?;
+        }
+
+        Ok(())
+    }
+
+    fn write(&mut self, rv: &RelVec) -> Result<(), Error> {
+        let journal = JournalRef {
+            base: self.base,
+            records: &rv.history[self.base.min(rv.history.len())..],
+        };
+        backup::write(&self.path, 0, |writer| {
+            Ok(serde_json::to_writer(writer, &journal)?)
+        })?;
+
+        self.unsaved = 0;
+        self.written = Instant::now();
+        Ok(())
+    }
+
+    /// Offers to replay the votes of an earlier session that stopped before
+    /// saving, also if this session doesn't autosave. Declined votes are
+    /// discarded. Without a terminal to ask on they are replayed, so piped
+    /// votes aren't taken as the answer.
+    pub fn recover(&mut self, rv: &mut RelVec) -> Result<(), Error> {
+        self.recover_asking(rv, io::stdin().is_tty())
+    }
+
+    /// Like `recover`, asks whether to replay the votes if `ask` is set
+    fn recover_asking(&mut self, rv: &mut RelVec, ask: bool) -> Result<(), Error> {
+        if !self.path.exists() {
+            return Ok(());
+        }
+
+        let journal: JournalFile =
+            serde_json::from_reader(BufReader::new(File::open(&self.path)?))?;
+        if journal.records.is_empty() {
+            return self.finish();
+        }
+
+        println!(
+            "\x1b[33mThere are {} unsaved votes of a session that stopped at {}.\x1b[0m",
+            journal.records.len(),
+            journal
+                .records
+                .last()
+                .map_or_else(String::new, |r| datetime(r.time))
+        );
+        if journal.base != rv.history.len() {
+            println!("The list was changed since, they are replayed on the current list.");
+        }
+        if ask {
+            println!("\x1b[31mDo you want to replay them? [Y/n]\x1b[0m");
+            let mut ans = String::new();
+            io::stdin().read_line(&mut ans)?;
+            if matches!(ans.chars().next(), Some('n') | Some('N')) {
+                println!("Discarded the unsaved votes");
+                return self.finish();
+            }
+        }
+
+        let skipped = replay(rv, &journal.records);
+        rv.close_period();
+        println!("Replayed {} votes", journal.records.len() - skipped);
+        if skipped > 0 {
+            println!(
+                "\x1b[31mSkipped {} votes on entries that are gone\x1b[0m",
+                skipped
+            );
+        }
+
+        // Still unsaved, keep them in this session's journal even if it
+        // doesn't autosave
+        self.seen = rv.history.len();
+        self.write(rv)
+    }
+
+    /// Removes the journal once its votes are saved
+    pub fn finish(&self) -> Result<(), Error> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Decides again as recorded, by the same voter and with the same time.
+/// Returns the number of records whose entries don't exist.
+fn replay(rv: &mut RelVec, records: &[Record]) -> usize {
+    let voter = rv.voter.take();
+    let mut skipped = 0;
+
+    for record in records {
+        rv.voter = record.voter.clone();
+        match rv.redo(record) {
+            Some(_) => {
+                if let Some(last) = rv.history.last_mut() {
+                    last.time = record.time;
+                }
+            }
+            None => skipped += 1,
+        }
+    }
+
+    rv.voter = voter;
+    skipped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, time::Duration};
+
+    use super::{replay, Journal, JournalFile};
+    use crate::{history::Outcome, rel_vec::RelVec};
+
+    fn list() -> RelVec {
+        let mut rv = RelVec::new();
+        for name in ["a", "b", "c"] {
+            rv.add(name.to_owned());
+        }
+        rv
+    }
+
+    #[test]
+    fn journal_update() {
+        let file = env::temp_dir().join("_relaty_journal_update.json");
+        let path = env::temp_dir().join("_relaty_journal_update.json.journal");
+        let mut rv = list();
+        rv.decide(0, 1, Outcome::First, "random");
+        let mut journal = Journal::new(&file, &rv, 2, None);
+
+        rv.decide(1, 2, Outcome::First, "random");
+        journal.update(&rv).unwrap();
+        assert!(!path.exists());
+
+        rv.decide(0, 2, Outcome::Second, "random");
+        journal.update(&rv).unwrap();
+        let written: JournalFile =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written.base, 1);
+        assert_eq!(written.records.len(), 2);
+
+        journal.finish().unwrap();
+        assert!(!path.exists());
+
+        // Time alone is enough once there is a new vote
+        let mut journal = Journal::new(&file, &rv, 10, Some(Duration::ZERO));
+        journal.update(&rv).unwrap();
+        assert!(!path.exists());
+        rv.decide(0, 1, Outcome::Draw, "random");
+        journal.update(&rv).unwrap();
+        assert!(path.exists());
+        journal.finish().unwrap();
+    }
+
+    #[test]
+    fn journal_replay() {
+        let mut voted = list();
+        voted.voter = Some("x".to_owned());
+        voted.decide_by(0, 1, Outcome::First, 2, "random");
+        voted.decide(1, 2, Outcome::RemoveSecond, "random");
+        voted.decide(0, 1, Outcome::Draw, "random");
+        voted.history[0].time = 1600000000;
+
+        let mut rv = list();
+        rv.voter = Some("y".to_owned());
+        assert_eq!(replay(&mut rv, &voted.history), 0);
+
+        assert_eq!(rv.len(), 2);
+        assert_eq!(rv.voter.as_deref(), Some("y"));
+        assert_eq!(rv.history[0].time, 1600000000);
+        assert_eq!(rv.history[0].margin, 2);
+        assert_eq!(rv.history[0].voter.as_deref(), Some("x"));
+        for (a, b) in rv.iter().zip(voted.iter()) {
+            assert_eq!((a.wins, a.votes), (b.wins, b.votes));
+        }
+
+        // Votes on entries that are gone are left out
+        let mut rv = list();
+        rv.inner.remove(0);
+        assert_eq!(replay(&mut rv, &voted.history), 2);
+    }
+
+    #[test]
+    fn journal_recover_without_autosave() {
+        let file = env::temp_dir().join("_relaty_journal_recover.json");
+        let path = env::temp_dir().join("_relaty_journal_recover.json.journal");
+        let mut rv = list();
+        let mut journal = Journal::new(&file, &rv, 1, None);
+        rv.decide(0, 1, Outcome::First, "random");
+        journal.update(&rv).unwrap();
+        assert!(path.exists());
+
+        // A session that doesn't autosave still replays the votes
+        let mut rv = list();
+        let mut journal = Journal::new(&file, &rv, 0, None);
+        journal.recover_asking(&mut rv, false).unwrap();
+        assert_eq!(rv.history.len(), 1);
+        assert_eq!((rv[0].wins, rv[0].votes), (1, 1));
+        assert!(path.exists());
+
+        journal.finish().unwrap();
+        assert!(!path.exists());
+    }
+}
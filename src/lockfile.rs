@@ -0,0 +1,166 @@
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process, thread,
+    time::Duration,
+};
+
+use fs2::FileExt;
+
+use crate::{
+    backup::sibling,
+    error::Error,
+    history::{datetime, now},
+};
+
+/// Time between two attempts while waiting for a lock
+const RETRY: Duration = Duration::from_millis(250);
+
+/// Exclusive lock of a list file, held from loading to saving it. The lock
+/// file `<FILE>.lock` names its owner and is removed when the lock is dropped.
+#[derive(Debug)]
+pub struct LockFile {
+    file: File,
+    path: PathBuf,
+}
+
+/// Whether an open file is still the one at `path`. The previous owner
+/// removes the lock file before releasing it.
+#[cfg(unix)]
+fn same_file(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_file(_file: &File, path: &Path) -> bool {
+    path.exists()
+}
+
+fn owner(file: &mut File) -> String {
+    let mut owner = String::new();
+    let _ = file.seek(SeekFrom::Start(0));
+    let _ = file.read_to_string(&mut owner);
+    owner.trim().to_owned()
+}
+
+impl LockFile {
+    /// Locks a list file. If another session holds the lock this fails, or
+    /// waits for it if `wait` is set. A lock file whose owner is gone is
+    /// taken over.
+    pub fn acquire(list: &Path, wait: bool) -> Result<Self, Error> {
+        let path = sibling(list, ".lock");
+        let mut waiting = false;
+
+        loop {
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)?;
+
+            match file.try_lock_exclusive() {
+                Ok(()) if !same_file(&file, &path) => continue,
+                Ok(()) => {
+                    let stale = owner(&mut file);
+                    if !stale.is_empty() {
+                        eprintln!("\x1b[33mTook over the stale lock of {}\x1b[0m", stale);
+                    }
+
+                    file.set_len(0)?;
+                    file.seek(SeekFrom::Start(0))?;
+                    writeln!(
+                        file,
+                        "process {} of {} since {}",
+                        process::id(),
+                        env::var("USER")
+                            .or_else(|_| env::var("USERNAME"))
+                            .unwrap_or_else(|_| "unknown user".to_owned()),
+                        datetime(now())
+                    )?;
+                    file.sync_all()?;
+
+                    return Ok(Self { file, path });
+                }
+                Err(e) if e.kind() == fs2::lock_contended_error().kind() => {
+                    let owner = owner(&mut file);
+                    if !wait {
+                        return Err(Error::Locked(list.display().to_string(), owner));
+                    }
+                    if !waiting {
+                        eprintln!("Waiting for {} to finish ...", owner);
+                        waiting = true;
+                    }
+                    thread::sleep(RETRY);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+        let _ = self.file.unlock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, thread, time::Duration};
+
+    use super::LockFile;
+    use crate::error::Error;
+
+    #[test]
+    fn lockfile_exclusive() {
+        let list = env::temp_dir().join("_relaty_lockfile_exclusive.json");
+        let path = env::temp_dir().join("_relaty_lockfile_exclusive.json.lock");
+
+        let lock = LockFile::acquire(&list, false).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().starts_with("process"));
+        assert!(matches!(
+            LockFile::acquire(&list, false),
+            Err(Error::Locked(_, owner)) if owner.starts_with("process")
+        ));
+
+        drop(lock);
+        assert!(!path.exists());
+        assert!(LockFile::acquire(&list, false).is_ok());
+    }
+
+    #[test]
+    fn lockfile_stale() {
+        let list = env::temp_dir().join("_relaty_lockfile_stale.json");
+        let path = env::temp_dir().join("_relaty_lockfile_stale.json.lock");
+
+        // Left behind by a session that crashed
+        fs::write(&path, "process 1 of x since 2020-01-01 00:00:00").unwrap();
+
+        let lock = LockFile::acquire(&list, false).unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("of x"));
+        drop(lock);
+    }
+
+    #[test]
+    fn lockfile_wait() {
+        let list = env::temp_dir().join("_relaty_lockfile_wait.json");
+
+        let lock = LockFile::acquire(&list, false).unwrap();
+        let holder = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(300));
+            drop(lock);
+        });
+
+        assert!(LockFile::acquire(&list, true).is_ok());
+        holder.join().unwrap();
+    }
+}
@@ -1,37 +1,116 @@
 #[macro_use]
 extern crate serde_derive;
 
+mod agreement;
+mod backup;
+mod bracket;
 mod cli;
 mod commands;
+mod condorcet;
+mod cycles;
 mod error;
+mod group;
+mod history;
+mod journal;
+mod lockfile;
+mod matrix;
+mod place;
+mod rating;
 mod rel_vec;
+mod sort;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod storage;
+mod swiss;
+mod tui;
 mod vote;
 
-use std::{convert::TryInto, io, str::FromStr};
+use std::{
+    convert::{TryFrom, TryInto},
+    env, io,
+    path::Path,
+    process,
+    str::FromStr,
+    time::Duration,
+};
 
-use crate::commands::{add, create, new, remove, reset, stats};
+use crate::agreement::agreement;
+use crate::backup::restore;
+use crate::bracket::bracket;
+use crate::commands::{add, create, fit, new, remove, reset, stats, undo};
+use crate::condorcet::Method;
+use crate::cycles::cycles;
 use crate::error::Error;
-use crate::vote::{vote, VoteStrategy};
-use clap::Shell;
+use crate::group::MAX_ITEMS;
+use crate::history::RepeatPolicy;
+use crate::lockfile::LockFile;
+use crate::matrix::{matrix, Format};
+use crate::place::place;
+use crate::rating::{Ranking, RatingOverrides};
+use crate::sort::sort;
+use crate::storage::{Backend, Output};
+use crate::vote::{vote, VoteOptions, VoteStrategy};
+use clap::{ArgMatches, Shell};
 use commands::{from, lock, print_file, print_screen};
 
-fn main() -> Result<(), Error> {
+/// Subcommands that change a list file
+const WRITING: [&str; 15] = [
+    "new", "from", "add", "place", "sort", "bracket", "remove", "reset", "lock", "unlock", "fit",
+    "undo", "restore", "cycles", "vote",
+];
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("\x1b[31m{}\x1b[0m", e);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Error> {
     let matches = crate::cli::build_cli().get_matches();
+    let backend = global(&matches, "backend")
+        .map(Backend::try_from)
+        .transpose()?;
+    let backups = match global(&matches, "backups") {
+        Some(n) => Some(n.parse()?),
+        None => backup::count(),
+    };
+    let _locks = lock_files(&matches)?;
 
     if let Some(matches) = matches.subcommand_matches("new") {
         if let Some(items) = matches.values_of("item") {
             let items = items.collect();
 
-            return create(matches.value_of("output").ok_or(Error::ArgError)?, items);
+            return create(
+                Output {
+                    path: matches.value_of("output").ok_or(Error::ArgError)?,
+                    backend,
+                    backups,
+                },
+                items,
+                matches.value_of("title"),
+            );
         } else {
-            return new(matches.value_of("output").ok_or(Error::ArgError)?);
+            return new(
+                Output {
+                    path: matches.value_of("output").ok_or(Error::ArgError)?,
+                    backend,
+                    backups,
+                },
+                matches.value_of("title"),
+            );
         }
     }
 
     if let Some(matches) = matches.subcommand_matches("from") {
         return from(
             matches.value_of("input").ok_or(Error::ArgError)?,
-            matches.value_of("output").ok_or(Error::ArgError)?,
+            Output {
+                path: matches.value_of("output").ok_or(Error::ArgError)?,
+                backend,
+                backups,
+            },
+            matches.value_of("title"),
         );
     }
 
@@ -43,6 +122,10 @@ fn main() -> Result<(), Error> {
                 matches.value_of("filter"),
                 matches.is_present("linenumbers"),
                 matches.is_present("nameonly"),
+                ranking(matches)?,
+                method(matches)?,
+                matches.value_of("voter"),
+                rating_overrides(matches)?,
             );
         } else {
             return print_screen(
@@ -50,22 +133,92 @@ fn main() -> Result<(), Error> {
                 matches.value_of("filter"),
                 matches.is_present("linenumbers"),
                 matches.is_present("nameonly"),
+                ranking(matches)?,
+                method(matches)?,
+                matches.value_of("voter"),
+                rating_overrides(matches)?,
             );
         }
     }
 
     if let Some(matches) = matches.subcommand_matches("add") {
         let input = matches.value_of("file").ok_or(Error::ArgError)?;
-        let output = matches.value_of("output").unwrap_or(input);
+        let output = Output {
+            path: matches.value_of("output").unwrap_or(input),
+            backend,
+            backups,
+        };
 
         if let Some(items) = matches.values_of("item") {
             return add(input, output, items);
         }
     }
 
+    if let Some(matches) = matches.subcommand_matches("place") {
+        let input = matches.value_of("file").ok_or(Error::ArgError)?;
+        let output = Output {
+            path: matches.value_of("output").unwrap_or(input),
+            backend,
+            backups,
+        };
+        let item = matches.value_of("item").ok_or(Error::ArgError)?;
+
+        return place(
+            input,
+            output,
+            item,
+            matches.is_present("info"),
+            rating_overrides(matches)?,
+        );
+    }
+
+    if let Some(matches) = matches.subcommand_matches("sort") {
+        let input = matches.value_of("file").ok_or(Error::ArgError)?;
+        let output = Output {
+            path: matches.value_of("output").unwrap_or(input),
+            backend,
+            backups,
+        };
+
+        return sort(
+            input,
+            output,
+            matches.is_present("info"),
+            matches.is_present("tui"),
+            matches.is_present("restart"),
+            rating_overrides(matches)?,
+            voter(matches),
+        );
+    }
+
+    if let Some(matches) = matches.subcommand_matches("bracket") {
+        let input = matches.value_of("file").ok_or(Error::ArgError)?;
+        let output = Output {
+            path: matches.value_of("output").unwrap_or(input),
+            backend,
+            backups,
+        };
+
+        return bracket(
+            input,
+            output,
+            matches.is_present("double"),
+            matches.is_present("random"),
+            matches.is_present("restart"),
+            ranking(matches)?,
+            matches.is_present("info"),
+            rating_overrides(matches)?,
+            voter(matches),
+        );
+    }
+
     if let Some(matches) = matches.subcommand_matches("remove") {
         let input = matches.value_of("file").ok_or(Error::ArgError)?;
-        let output = matches.value_of("output").unwrap_or(input);
+        let output = Output {
+            path: matches.value_of("output").unwrap_or(input),
+            backend,
+            backups,
+        };
         let filter = matches.value_of("filter").ok_or(Error::ArgError)?;
 
         return remove(input, output, filter);
@@ -73,7 +226,11 @@ fn main() -> Result<(), Error> {
 
     if let Some(matches) = matches.subcommand_matches("reset") {
         let input = matches.value_of("file").ok_or(Error::ArgError)?;
-        let output = matches.value_of("output").unwrap_or(input);
+        let output = Output {
+            path: matches.value_of("output").unwrap_or(input),
+            backend,
+            backups,
+        };
         let filter = matches.value_of("filter").ok_or(Error::ArgError)?;
 
         return reset(input, output, filter);
@@ -81,7 +238,11 @@ fn main() -> Result<(), Error> {
 
     if let Some(matches) = matches.subcommand_matches("lock") {
         let input = matches.value_of("file").ok_or(Error::ArgError)?;
-        let output = matches.value_of("output").unwrap_or(input);
+        let output = Output {
+            path: matches.value_of("output").unwrap_or(input),
+            backend,
+            backups,
+        };
         let filter = matches.value_of("filter").ok_or(Error::ArgError)?;
 
         return lock(input, output, filter, true);
@@ -89,21 +250,116 @@ fn main() -> Result<(), Error> {
 
     if let Some(matches) = matches.subcommand_matches("unlock") {
         let input = matches.value_of("file").ok_or(Error::ArgError)?;
-        let output = matches.value_of("output").unwrap_or(input);
+        let output = Output {
+            path: matches.value_of("output").unwrap_or(input),
+            backend,
+            backups,
+        };
         let filter = matches.value_of("filter").ok_or(Error::ArgError)?;
 
         return lock(input, output, filter, false);
     }
 
+    if let Some(matches) = matches.subcommand_matches("fit") {
+        let input = matches.value_of("file").ok_or(Error::ArgError)?;
+        let output = Output {
+            path: matches.value_of("output").unwrap_or(input),
+            backend,
+            backups,
+        };
+
+        return fit(input, output, rating_overrides(matches)?);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("undo") {
+        let input = matches.value_of("file").ok_or(Error::ArgError)?;
+        let output = Output {
+            path: matches.value_of("output").unwrap_or(input),
+            backend,
+            backups,
+        };
+        let count = matches
+            .value_of("count")
+            .ok_or(Error::ArgError)?
+            .parse::<usize>()?;
+
+        return undo(input, output, count);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("restore") {
+        let backup = matches.value_of("backup").map(str::parse).transpose()?;
+
+        let output = Output {
+            path: matches.value_of("file").ok_or(Error::ArgError)?,
+            backend,
+            backups,
+        };
+
+        return restore(output, backup);
+    }
+
     if let Some(matches) = matches.subcommand_matches("stats") {
         let input = matches.value_of("file").ok_or(Error::ArgError)?;
 
-        return stats(input);
+        return stats(
+            input,
+            ranking(matches)?,
+            matches.value_of("voter"),
+            rating_overrides(matches)?,
+        );
+    }
+
+    if let Some(matches) = matches.subcommand_matches("agreement") {
+        return agreement(
+            matches.value_of("file").ok_or(Error::ArgError)?,
+            ranking(matches)?,
+            rating_overrides(matches)?,
+        );
+    }
+
+    if let Some(matches) = matches.subcommand_matches("cycles") {
+        let input = matches.value_of("file").ok_or(Error::ArgError)?;
+        let output = Output {
+            path: matches.value_of("output").unwrap_or(input),
+            backend,
+            backups,
+        };
+
+        return cycles(
+            input,
+            output,
+            matches.is_present("info"),
+            matches.is_present("tui"),
+            rating_overrides(matches)?,
+            voter(matches),
+        );
+    }
+
+    if let Some(matches) = matches.subcommand_matches("matrix") {
+        let top = matches.value_of("top").map(str::parse).transpose()?;
+
+        return matrix(
+            matches.value_of("file").ok_or(Error::ArgError)?,
+            matches.value_of("output"),
+            matches
+                .value_of("format")
+                .map(Format::try_from)
+                .transpose()?,
+            matches.value_of("filter"),
+            top,
+            ranking(matches)?,
+            matches.value_of("voter"),
+            rating_overrides(matches)?,
+        );
     }
 
     if let Some(matches) = matches.subcommand_matches("vote") {
         let input = matches.value_of("file").ok_or(Error::ArgError)?;
-        let output = matches.value_of("output").unwrap_or(input);
+        let output = Output {
+            path: matches.value_of("output").unwrap_or(input),
+            backend,
+            backups,
+        };
         let rounds = matches
             .value_of("rounds")
             .ok_or(Error::ArgError)?
@@ -112,10 +368,56 @@ fn main() -> Result<(), Error> {
             .value_of("strategy")
             .ok_or(Error::ArgError)?
             .try_into()?;
+        let repeat: RepeatPolicy = matches
+            .value_of("repeat")
+            .ok_or(Error::ArgError)?
+            .try_into()?;
+        let items = matches
+            .value_of("items")
+            .ok_or(Error::ArgError)?
+            .parse::<usize>()?;
+        if !(2..=MAX_ITEMS).contains(&items) {
+            return Err(Error::OutOfRange(
+                "-n",
+                format!("between 2 and {}", MAX_ITEMS),
+            ));
+        }
+        // Swiss rounds pair every entry once, groups would mix in others
+        if items > 2 && matches!(strategy, VoteStrategy::Swiss) {
+            return Err(Error::OutOfRange("-n", "2 with -s swiss".to_owned()));
+        }
         let info = matches.is_present("info");
+        let ranking = ranking(matches)?;
+        let autosave = matches
+            .value_of("autosave")
+            .ok_or(Error::ArgError)?
+            .parse::<usize>()?;
+        let autosave_time = matches
+            .value_of("autosavetime")
+            .ok_or(Error::ArgError)?
+            .parse::<u64>()?;
 
-        println!("Using strategy \"{}\"", strategy.to_string());
-        return vote(input, output, rounds, strategy.choose_function(), info);
+        println!("Using strategy \"{}\"", strategy);
+        return vote(
+            input,
+            output,
+            VoteOptions {
+                rounds,
+                strategy,
+                repeat,
+                ranking,
+                items,
+                info,
+                tui: matches.is_present("tui"),
+                restart: matches.is_present("restart"),
+                overrides: rating_overrides(matches)?,
+                voter: voter(matches),
+                autosave: (
+                    autosave,
+                    Some(Duration::from_secs(autosave_time)).filter(|t| !t.is_zero()),
+                ),
+            },
+        );
     }
 
     if let Some(matches) = matches.subcommand_matches("completions") {
@@ -134,3 +436,74 @@ fn main() -> Result<(), Error> {
         .print_long_help()
         .map_err(|_| Error::ArgError)
 }
+
+/// Locks the files a subcommand reads and writes until it's done
+fn lock_files(matches: &ArgMatches) -> Result<Vec<LockFile>, Error> {
+    let (name, matches) = match matches.subcommand() {
+        (name, Some(matches)) if WRITING.contains(&name) => (name, matches),
+        _ => return Ok(Vec::new()),
+    };
+    // `from` reads a text file
+    let args: &[&str] = if name == "from" {
+        &["output"]
+    } else {
+        &["file", "output"]
+    };
+
+    let mut files: Vec<&str> = args.iter().filter_map(|a| matches.value_of(a)).collect();
+    // Same order in every session
+    files.sort_unstable();
+    files.dedup();
+    files
+        .into_iter()
+        .map(|f| LockFile::acquire(Path::new(f), matches.is_present("wait")))
+        .collect()
+}
+
+fn ranking(matches: &ArgMatches) -> Result<Ranking, Error> {
+    matches.value_of("by").ok_or(Error::ArgError)?.try_into()
+}
+
+fn method(matches: &ArgMatches) -> Result<Option<Method>, Error> {
+    matches.value_of("method").map(Method::try_from).transpose()
+}
+
+/// Voter from the command line, `$RELATY_VOTER` or the user name
+fn voter(matches: &ArgMatches) -> Option<String> {
+    matches
+        .value_of("voter")
+        .map(str::to_owned)
+        .or_else(|| env::var("RELATY_VOTER").ok())
+        .or_else(|| env::var("USER").ok())
+        .or_else(|| env::var("USERNAME").ok())
+        .filter(|v| !v.is_empty())
+}
+
+/// Value of a global argument, given before or after the subcommand
+fn global<'a>(matches: &'a ArgMatches, name: &str) -> Option<&'a str> {
+    matches
+        .subcommand()
+        .1
+        .and_then(|m| m.value_of(name))
+        .or_else(|| matches.value_of(name))
+}
+
+fn rating_overrides(matches: &ArgMatches) -> Result<RatingOverrides, Error> {
+    let mut overrides = RatingOverrides::default();
+
+    if let Some(k) = matches.value_of("kfactor") {
+        overrides.k_factor = Some(k.parse()?);
+    }
+    if let Some(initial) = matches.value_of("initialelo") {
+        overrides.initial_elo = Some(initial.parse()?);
+    }
+    if let Some(prior) = matches.value_of("prior") {
+        let prior: f64 = prior.parse()?;
+        if prior <= 0.0 {
+            return Err(Error::OutOfRange("--prior", "greater than 0".to_owned()));
+        }
+        overrides.prior = Some(prior);
+    }
+
+    Ok(overrides)
+}
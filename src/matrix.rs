@@ -0,0 +1,332 @@
+use std::{
+    convert::TryFrom,
+    fmt,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use regex::Regex;
+
+use crate::{
+    error::Error,
+    rating::{Ranking, RatingOverrides},
+    rel_vec::RelVec,
+};
+
+/// Output format of the head-to-head matrix
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Text,
+    Csv,
+    Json,
+    Html,
+}
+
+/// Matrix as written to a JSON file
+#[derive(Serialize)]
+struct MatrixFile<'a> {
+    entries: &'a [&'a str],
+    /// Wins of the row against the column, `null` on the diagonal
+    wins: Vec<Vec<Option<f64>>>,
+}
+
+impl Format {
+    pub fn formats() -> [&'static str; 4] {
+        ["text", "csv", "json", "html"]
+    }
+
+    /// Format by the extension of a file, text if it's unknown
+    pub fn of_path(path: &str) -> Self {
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("csv") => Format::Csv,
+            Some("json") => Format::Json,
+            Some("html") | Some("htm") => Format::Html,
+            _ => Format::Text,
+        }
+    }
+
+    /// Matrix of wins of the entries against each other in this format
+    pub fn render(&self, title: &str, names: &[&str], wins: &[Vec<f64>]) -> Result<String, Error> {
+        Ok(match self {
+            Format::Text => text(names, wins),
+            Format::Csv => csv(names, wins),
+            Format::Json => {
+                let file = MatrixFile {
+                    entries: names,
+                    wins: cells(wins, |w| w),
+                };
+                serde_json::to_string(&file)? + "\n"
+            }
+            Format::Html => html(title, names, wins),
+        })
+    }
+}
+
+/// Cells of the matrix without the diagonal
+fn cells<T>(wins: &[Vec<f64>], f: impl Fn(f64) -> T) -> Vec<Vec<Option<T>>> {
+    wins.iter()
+        .enumerate()
+        .map(|(a, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(b, &w)| if a == b { None } else { Some(f(w)) })
+                .collect()
+        })
+        .collect()
+}
+
+/// Table with numbered columns, wins in green and losses in red
+fn text(names: &[&str], wins: &[Vec<f64>]) -> String {
+    let index_width = names.len().to_string().len();
+    let name_width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+    let cells = cells(wins, |w| w.to_string());
+    let width = cells
+        .iter()
+        .flatten()
+        .flatten()
+        .map(|c| c.len())
+        .max()
+        .unwrap_or(1)
+        .max(index_width);
+
+    let mut s = format!("{:iw$} {:nw$}", "", "", iw = index_width, nw = name_width);
+    for b in 0..names.len() {
+        s += &format!(" \x1b[34m{:>w$}\x1b[0m", b + 1, w = width);
+    }
+    s += "\n";
+
+    for (a, row) in cells.iter().enumerate() {
+        s += &format!(
+            "\x1b[34m{:>iw$}\x1b[0m {:nw$}",
+            a + 1,
+            names[a],
+            iw = index_width,
+            nw = name_width
+        );
+        for (b, cell) in row.iter().enumerate() {
+            let color = if wins[a][b] > wins[b][a] {
+                "32"
+            } else if wins[a][b] < wins[b][a] {
+                "31"
+            } else {
+                "90"
+            };
+            let cell = cell.as_deref().unwrap_or("-");
+            s += &format!(" \x1b[{}m{:>w$}\x1b[0m", color, cell, w = width);
+        }
+        s += "\n";
+    }
+
+    s
+}
+
+/// Field of a CSV file, quoted if needed
+fn csv_field(s: &str) -> String {
+    if s.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+/// Names in the first row and column, the diagonal is empty
+fn csv(names: &[&str], wins: &[Vec<f64>]) -> String {
+    let mut s = String::new();
+
+    for name in names {
+        s += ",";
+        s += &csv_field(name);
+    }
+    s += "\n";
+
+    for (a, row) in cells(wins, |w| w.to_string()).into_iter().enumerate() {
+        s += &csv_field(names[a]);
+        for cell in row {
+            s += ",";
+            s += &cell.unwrap_or_default();
+        }
+        s += "\n";
+    }
+
+    s
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Page with a table colored from red (always lost) to green (always won)
+fn html(title: &str, names: &[&str], wins: &[Vec<f64>]) -> String {
+    let mut s = format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{}</title>
+<style>
+table {{ border-collapse: collapse; font-family: sans-serif; }}
+th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: center; }}
+th.row {{ text-align: left; }}
+</style>
+</head>
+<body>
+<table>
+<tr><th></th>",
+        escape(title)
+    );
+    for name in names {
+        s += &format!("<th>{}</th>", escape(name));
+    }
+    s += "</tr>\n";
+
+    for (a, row) in wins.iter().enumerate() {
+        s += &format!("<tr><th class=\"row\">{}</th>", escape(names[a]));
+        for (b, &w) in row.iter().enumerate() {
+            let games = w + wins[b][a];
+            if a == b || games == 0.0 {
+                s += "<td style=\"background: #eee\"></td>";
+            } else {
+                s += &format!(
+                    "<td style=\"background: hsl({:.0}, 70%, 80%)\" title=\"{} against {}: {} to {}\">{}</td>",
+                    120.0 * w / games,
+                    escape(names[a]),
+                    escape(names[b]),
+                    w,
+                    wins[b][a],
+                    w
+                );
+            }
+        }
+        s += "</tr>\n";
+    }
+    s += "</table>\n</body>\n</html>\n";
+
+    s
+}
+
+/// Prints or writes the wins of the entries against each other
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn matrix(
+    input: &str,
+    output: Option<&str>,
+    format: Option<Format>,
+    filter: Option<&str>,
+    top: Option<usize>,
+    ranking: Ranking,
+    voter: Option<&str>,
+    overrides: RatingOverrides,
+) -> Result<(), Error> {
+    let mut rv = RelVec::load(input)?;
+    overrides.apply(&mut rv.config);
+    if let Some(voter) = voter {
+        rv = rv.of_voter(voter);
+    }
+    let re = match filter {
+        Some(filter) => Regex::new(filter)?,
+        None => Regex::new(".*?")?,
+    };
+
+    rv.sort_ranking(ranking);
+    let wins = rv.pairwise();
+    let selected: Vec<usize> = (0..rv.len())
+        .filter(|&i| re.is_match(&rv[i].name))
+        .take(top.unwrap_or(usize::MAX))
+        .collect();
+    let names: Vec<&str> = selected.iter().map(|&i| rv[i].name.as_str()).collect();
+    let wins: Vec<Vec<f64>> = selected
+        .iter()
+        .map(|&a| selected.iter().map(|&b| wins[a][b]).collect())
+        .collect();
+
+    match output {
+        Some(output) => {
+            let format = format.unwrap_or_else(|| Format::of_path(output));
+            let mut writer = BufWriter::new(File::create(output)?);
+            writer.write_all(format.render(input, &names, &wins)?.as_bytes())?;
+        }
+        None => print!(
+            "{}",
+            format
+                .unwrap_or(Format::Text)
+                .render(input, &names, &wins)?
+        ),
+    }
+
+    Ok(())
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Text => write!(f, "text"),
+            Format::Csv => write!(f, "csv"),
+            Format::Json => write!(f, "json"),
+            Format::Html => write!(f, "html"),
+        }
+    }
+}
+
+impl TryFrom<&str> for Format {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "text" => Ok(Format::Text),
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            "html" => Ok(Format::Html),
+            _ => Err(Error::ArgError),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Format;
+
+    fn wins() -> Vec<Vec<f64>> {
+        vec![vec![0.0, 2.5], vec![0.5, 0.0]]
+    }
+
+    #[test]
+    fn matrix_of_path() {
+        assert_eq!(Format::of_path("m.csv"), Format::Csv);
+        assert_eq!(Format::of_path("dir/m.json"), Format::Json);
+        assert_eq!(Format::of_path("m.htm"), Format::Html);
+        assert_eq!(Format::of_path("m.txt"), Format::Text);
+        assert_eq!(Format::of_path("m"), Format::Text);
+    }
+
+    #[test]
+    fn matrix_csv() {
+        assert_eq!(
+            Format::Csv.render("", &["a", "b, \"c\""], &wins()).unwrap(),
+            ",a,\"b, \"\"c\"\"\"\na,,2.5\n\"b, \"\"c\"\"\",0.5,\n"
+        );
+    }
+
+    #[test]
+    fn matrix_json() {
+        assert_eq!(
+            Format::Json.render("", &["a", "b"], &wins()).unwrap(),
+            "{\"entries\":[\"a\",\"b\"],\"wins\":[[null,2.5],[0.5,null]]}\n"
+        );
+    }
+
+    #[test]
+    fn matrix_html() {
+        let html = Format::Html
+            .render("<list>", &["a&b", "c"], &wins())
+            .unwrap();
+
+        assert!(html.contains("<title>&lt;list&gt;</title>"));
+        assert!(html.contains("<th class=\"row\">a&amp;b</th>"));
+        // Won 2.5 of 3 games
+        assert!(html.contains("hsl(100, 70%, 80%)"));
+        assert!(html.contains("hsl(20, 70%, 80%)"));
+    }
+}
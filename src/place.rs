@@ -0,0 +1,147 @@
+use std::cmp::Ordering;
+
+use crate::{
+    error::Error,
+    rating::RatingOverrides,
+    rel_vec::RelVec,
+    storage::Output,
+    vote::{read_command, show_pair},
+};
+
+/// Inserts an item into the ranking by binary search over the entries sorted
+/// by percentage. Needs about log2(n) comparisons.
+pub(crate) fn place(
+    input: &str,
+    output: Output,
+    item: &str,
+    info: bool,
+    overrides: RatingOverrides,
+) -> Result<(), Error> {
+    let mut rv = RelVec::load(input)?;
+    overrides.apply(&mut rv.config);
+
+    let added = rv.position(item).is_none();
+    if added {
+        rv.add(item.to_owned());
+    }
+    let new = rv.position(item).ok_or(Error::ArgError)?;
+    let placed = insert(&mut rv, new, |rv, other, round, max| loop {
+        println!("\x1b[90mComparison {} of at most {}\x1b[0m", round, max);
+        show_pair(rv, new, other, info);
+
+        let commands = [('=', "Equally good".to_owned()), ('q', "Quit".to_owned())];
+        let answer = match read_command(&commands)? {
+            Some('1') => Some(Ordering::Greater),
+            Some('2') => Some(Ordering::Less),
+            Some('=') => Some(Ordering::Equal),
+            Some('q') | None => None,
+            _ => {
+                println!("\x1b[31munknown command\x1b[0m");
+                continue;
+            }
+        };
+
+        println!("======================");
+        return Ok(answer);
+    })?;
+
+    match placed {
+        Some(position) => println!(
+            "Placed \x1b[34m{}\x1b[0m at position \x1b[34m{}\x1b[0m",
+            item, position
+        ),
+        // An item that wasn't placed isn't added either
+        None if added => {
+            rv.pop();
+        }
+        None => {}
+    }
+
+    rv.save_to(output)
+}
+
+/// Binary search for the position of entry `new` among the others sorted by
+/// percentage. `compare` gets the other entry, the number of the comparison
+/// and the maximum number and tells whether `new` is better, or `None` to
+/// quit. The comparisons only steer the search and are not recorded as
+/// decisions: afterwards the entry is seeded between its neighbours. Returns
+/// the position from 1, `None` if quit.
+fn insert<F>(rv: &mut RelVec, new: usize, mut compare: F) -> Result<Option<usize>, Error>
+where
+    F: FnMut(&RelVec, usize, u32, u32) -> Result<Option<Ordering>, Error>,
+{
+    let mut order: Vec<usize> = (0..rv.len()).filter(|&i| i != new).collect();
+    order.sort_by(|&a, &b| rv[a].compare_percentage(&rv[b]).reverse());
+
+    let (mut lo, mut hi) = (0, order.len());
+    let max = (order.len() + 1).next_power_of_two().trailing_zeros();
+    let mut round = 0;
+
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+
+        round += 1;
+        match compare(rv, order[mid], round, max)? {
+            Some(Ordering::Greater) => hi = mid,
+            Some(Ordering::Less) => lo = mid + 1,
+            Some(Ordering::Equal) => {
+                lo = mid;
+                hi = mid;
+            }
+            None => return Ok(None),
+        }
+    }
+
+    let above = if lo > 0 { Some(order[lo - 1]) } else { None };
+    let below = order.get(lo).copied();
+    rv.seed(new, above, below);
+
+    Ok(Some(lo + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use super::insert;
+    use crate::{history::Outcome, rel_vec::RelVec};
+
+    #[test]
+    fn place_undo() {
+        let mut rv = RelVec::new();
+        for name in ["a", "b", "c", "new"] {
+            rv.add(name.to_owned());
+        }
+        rv.decide(0, 1, Outcome::First, "random");
+        rv.decide(1, 2, Outcome::First, "random");
+        let before: Vec<(u32, u32)> = rv.iter().map(|e| (e.wins, e.votes)).collect();
+
+        // Better than b, worse than a
+        let placed = insert(&mut rv, 3, |rv, other, _, _| {
+            Ok(Some(match rv[other].name.as_str() {
+                "a" => Ordering::Less,
+                _ => Ordering::Greater,
+            }))
+        })
+        .unwrap();
+        assert_eq!(placed, Some(2));
+        assert_eq!(rv.history.len(), 2);
+        for (i, e) in rv.iter().take(3).enumerate() {
+            assert_eq!((e.wins, e.votes), before[i]);
+        }
+        // Only the ratings are seeded, wins and votes need decisions
+        assert_eq!((rv[3].wins, rv[3].votes), (0, 0));
+        let config = rv.config;
+        let elo = |i: usize| rv[i].elo_rating(&config.elo);
+        assert!(elo(3) < elo(0) && elo(3) > elo(1));
+        let glicko = |i: usize| rv[i].glicko_rating(&config.glicko).rating;
+        assert!(glicko(3) < glicko(0) && glicko(3) > glicko(1));
+        let seeded = rv[3].elo;
+
+        // Undo takes back the last vote, not the placement
+        rv.undo();
+        assert_eq!((rv[1].wins, rv[1].votes), (0, 1));
+        assert_eq!((rv[2].wins, rv[2].votes), (0, 0));
+        assert_eq!(rv[3].elo, seeded);
+    }
+}
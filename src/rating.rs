@@ -0,0 +1,574 @@
+use std::{cmp::Ordering, collections::HashMap, convert::TryFrom, f64::consts::PI, fmt};
+
+use crate::{error::Error, rel_vec::RelEntry};
+
+/// Settings of the Elo model
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EloConfig {
+    pub k_factor: f64,
+    pub initial: f64,
+}
+
+impl EloConfig {
+    /// Expected score of a player rated `a` against a player rated `b`
+    pub fn expected(a: f64, b: f64) -> f64 {
+        1.0 / (1.0 + 10f64.powf((b - a) / 400.0))
+    }
+
+    /// Rating change of the player rated `a` after scoring `score` (1 = win, 0 = loss)
+    /// against the player rated `b`. The opponent changes by the negated amount.
+    pub fn delta(&self, a: f64, b: f64, score: f64) -> f64 {
+        self.k_factor * (score - Self::expected(a, b))
+    }
+}
+
+impl Default for EloConfig {
+    fn default() -> Self {
+        Self {
+            k_factor: 32.0,
+            initial: 1500.0,
+        }
+    }
+}
+
+/// Glicko-2 rating of a single entry, on the Glicko scale
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Glicko {
+    #[serde(rename = "r")]
+    pub rating: f64,
+    #[serde(rename = "d")]
+    pub deviation: f64,
+    #[serde(rename = "s")]
+    pub volatility: f64,
+}
+
+/// Factor between the Glicko and the Glicko-2 scale
+const GLICKO_SCALE: f64 = 173.7178;
+
+impl Glicko {
+    /// Rating that is very likely (~95%) exceeded by the true rating
+    pub fn conservative(&self) -> f64 {
+        self.rating - 2.0 * self.deviation
+    }
+
+    /// Expected decrease of the variances of both ratings (on the Glicko-2
+    /// scale) by a single game between them. Largest for uncertain ratings
+    /// and close matches.
+    pub fn information(&self, other: &Glicko) -> f64 {
+        let gain = |p: &Glicko, o: &Glicko| {
+            let g = Glicko::g(o.phi());
+            let e = 1.0 / (1.0 + (-g * (p.mu() - o.mu())).exp());
+            let phi2 = p.phi() * p.phi();
+            phi2 - 1.0 / (1.0 / phi2 + g * g * e * (1.0 - e))
+        };
+
+        gain(self, other) + gain(other, self)
+    }
+
+    fn mu(&self) -> f64 {
+        (self.rating - 1500.0) / GLICKO_SCALE
+    }
+
+    fn phi(&self) -> f64 {
+        self.deviation / GLICKO_SCALE
+    }
+
+    fn g(phi: f64) -> f64 {
+        1.0 / (1.0 + 3.0 * phi * phi / (PI * PI)).sqrt()
+    }
+}
+
+/// Settings of the Glicko-2 model
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GlickoConfig {
+    pub initial: Glicko,
+    /// Constrains the change in volatility over time
+    pub tau: f64,
+}
+
+impl GlickoConfig {
+    /// Rating after a rating period with the given games, each against the
+    /// opponent's rating at the start of the period with the player's score
+    pub fn update(&self, player: Glicko, games: &[(Glicko, f64)]) -> Glicko {
+        if games.is_empty() {
+            return self.idle(player);
+        }
+
+        let mu = player.mu();
+        let phi = player.phi();
+        let sigma = player.volatility;
+
+        let mut v_inv = 0.0;
+        let mut sum = 0.0;
+        for (opponent, score) in games {
+            let g = Glicko::g(opponent.phi());
+            let e = 1.0 / (1.0 + (-g * (mu - opponent.mu())).exp());
+            v_inv += g * g * e * (1.0 - e);
+            sum += g * (score - e);
+        }
+        let v = 1.0 / v_inv;
+        let delta = v * sum;
+
+        // New volatility by the Illinois algorithm
+        let a = (sigma * sigma).ln();
+        let tau2 = self.tau * self.tau;
+        let f = |x: f64| {
+            let ex = x.exp();
+            let d = phi * phi + v + ex;
+            ex * (delta * delta - phi * phi - v - ex) / (2.0 * d * d) - (x - a) / tau2
+        };
+        let mut big_a = a;
+        let mut big_b = if delta * delta > phi * phi + v {
+            (delta * delta - phi * phi - v).ln()
+        } else {
+            let mut k = 1.0;
+            while f(a - k * self.tau) < 0.0 {
+                k += 1.0;
+            }
+            a - k * self.tau
+        };
+        let mut f_a = f(big_a);
+        let mut f_b = f(big_b);
+        while (big_b - big_a).abs() > 1e-6 {
+            let c = big_a + (big_a - big_b) * f_a / (f_b - f_a);
+            let f_c = f(c);
+            if f_c * f_b <= 0.0 {
+                big_a = big_b;
+                f_a = f_b;
+            } else {
+                f_a /= 2.0;
+            }
+            big_b = c;
+            f_b = f_c;
+        }
+        let sigma = (big_a / 2.0).exp();
+
+        let phi_star = (phi * phi + sigma * sigma).sqrt();
+        let phi = 1.0 / (1.0 / (phi_star * phi_star) + 1.0 / v).sqrt();
+        let mu = mu + phi * phi * sum;
+
+        Glicko {
+            rating: mu * GLICKO_SCALE + 1500.0,
+            deviation: phi * GLICKO_SCALE,
+            volatility: sigma,
+        }
+    }
+
+    /// Rating after a rating period without games, only the deviation grows
+    pub fn idle(&self, player: Glicko) -> Glicko {
+        let phi = player.phi();
+        let deviation = (phi * phi + player.volatility * player.volatility).sqrt() * GLICKO_SCALE;
+
+        Glicko {
+            deviation: deviation.min(self.initial.deviation),
+            ..player
+        }
+    }
+}
+
+impl Default for GlickoConfig {
+    fn default() -> Self {
+        Self {
+            initial: Glicko {
+                rating: 1500.0,
+                deviation: 350.0,
+                volatility: 0.06,
+            },
+            tau: 0.5,
+        }
+    }
+}
+
+/// Games of the current Glicko-2 rating period. A period spans one vote session.
+#[derive(Clone, Debug, Default)]
+pub struct RatingPeriod {
+    start: HashMap<String, Glicko>,
+    games: Vec<(String, String, f64)>,
+}
+
+impl RatingPeriod {
+    /// Adds a game with the score of `a` (1 = win, 0 = loss)
+    pub fn add(&mut self, a: &RelEntry, b: &RelEntry, score: f64, config: &GlickoConfig) {
+        self.start
+            .entry(a.name.clone())
+            .or_insert_with(|| a.glicko_rating(config));
+        self.start
+            .entry(b.name.clone())
+            .or_insert_with(|| b.glicko_rating(config));
+        self.games.push((a.name.clone(), b.name.clone(), score));
+    }
+
+    /// Rating of an entry after all games of this period so far
+    pub fn rate(&self, name: &str, config: &GlickoConfig) -> Option<Glicko> {
+        let player = *self.start.get(name)?;
+        let games: Vec<(Glicko, f64)> = self
+            .games
+            .iter()
+            .filter_map(|(a, b, score)| {
+                if a == name {
+                    Some((self.start[b], *score))
+                } else if b == name {
+                    Some((self.start[a], 1.0 - score))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        Some(config.update(player, &games))
+    }
+
+    /// Removes the last game between two entries
+    pub fn remove(&mut self, a: &str, b: &str) {
+        let pos = self
+            .games
+            .iter()
+            .rposition(|(x, y, _)| (x == a && y == b) || (x == b && y == a));
+
+        if let Some(pos) = pos {
+            self.games.remove(pos);
+        }
+
+        let games = &self.games;
+        self.start
+            .retain(|name, _| games.iter().any(|(x, y, _)| x == name || y == name));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.start.contains_key(name)
+    }
+}
+
+/// Settings of the Bradley–Terry model
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BradleyTerryConfig {
+    /// Virtual wins and losses of every entry against an opponent of strength 1.
+    /// Keeps strengths finite for entries that never won or never lost.
+    pub prior: f64,
+}
+
+const BT_MAX_ITERATIONS: usize = 10_000;
+
+impl BradleyTerryConfig {
+    /// Maximum likelihood strengths of `n` entries given `(winner, loser, weight)`
+    /// results, computed with the MM algorithm
+    pub fn fit(&self, n: usize, results: &[(usize, usize, f64)]) -> Vec<f64> {
+        let mut wins = vec![self.prior; n];
+        let mut pairs: HashMap<(usize, usize), f64> = HashMap::new();
+
+        for &(w, l, weight) in results {
+            if w == l {
+                continue;
+            }
+            wins[w] += weight;
+            *pairs.entry((w.min(l), w.max(l))).or_insert(0.0) += weight;
+        }
+
+        let mut p = vec![1.0; n];
+        for _ in 0..BT_MAX_ITERATIONS {
+            let mut denom: Vec<f64> = p.iter().map(|pi| 2.0 * self.prior / (pi + 1.0)).collect();
+            for (&(i, j), c) in &pairs {
+                let d = c / (p[i] + p[j]);
+                denom[i] += d;
+                denom[j] += d;
+            }
+
+            let mut change: f64 = 0.0;
+            for i in 0..n {
+                if denom[i] > 0.0 {
+                    let new = wins[i] / denom[i];
+                    change = change.max((new - p[i]).abs() / p[i]);
+                    p[i] = new;
+                }
+            }
+            if change < 1e-9 {
+                break;
+            }
+        }
+
+        p
+    }
+}
+
+impl Default for BradleyTerryConfig {
+    fn default() -> Self {
+        Self { prior: 1.0 }
+    }
+}
+
+/// Settings of all rating models
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RatingConfig {
+    pub elo: EloConfig,
+    pub glicko: GlickoConfig,
+    pub bradley_terry: BradleyTerryConfig,
+}
+
+/// Settings given on the command line. They take precedence over those stored
+/// in the list file.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RatingOverrides {
+    pub k_factor: Option<f64>,
+    pub initial_elo: Option<f64>,
+    pub prior: Option<f64>,
+}
+
+impl RatingOverrides {
+    pub fn apply(&self, config: &mut RatingConfig) {
+        if let Some(k) = self.k_factor {
+            config.elo.k_factor = k;
+        }
+        if let Some(initial) = self.initial_elo {
+            config.elo.initial = initial;
+        }
+        if let Some(prior) = self.prior {
+            config.bradley_terry.prior = prior;
+        }
+    }
+}
+
+/// What entries are ranked by
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ranking {
+    Percentage,
+    Elo,
+    Glicko,
+    BradleyTerry,
+    Rank,
+}
+
+impl Ranking {
+    pub fn rankings() -> [&'static str; 5] {
+        ["percentage", "elo", "glicko", "bt", "rank"]
+    }
+
+    /// Numeric score of an entry, higher is better
+    pub fn value(&self, entry: &RelEntry, config: &RatingConfig) -> f64 {
+        match self {
+            Ranking::Percentage => entry.percentage(),
+            Ranking::Elo => entry.elo_rating(&config.elo),
+            Ranking::Glicko => entry.glicko_rating(&config.glicko).conservative(),
+            Ranking::BradleyTerry => entry.strength(),
+            Ranking::Rank => entry.rank.map_or(f64::NEG_INFINITY, |r| -f64::from(r)),
+        }
+    }
+
+    pub fn compare(&self, a: &RelEntry, b: &RelEntry, config: &RatingConfig) -> Ordering {
+        match self {
+            Ranking::Percentage => a.compare_percentage(b),
+            _ => self
+                .value(a, config)
+                .partial_cmp(&self.value(b, config))
+                .unwrap_or(Ordering::Equal),
+        }
+    }
+
+    /// Extra column shown next to an entry, if the ranking isn't already part of it
+    pub fn describe(&self, entry: &RelEntry, config: &RatingConfig) -> Option<String> {
+        match self {
+            Ranking::Percentage => None,
+            Ranking::Elo => Some(format!("{:.1} Elo", entry.elo_rating(&config.elo))),
+            Ranking::Glicko => {
+                let g = entry.glicko_rating(&config.glicko);
+                Some(format!(
+                    "{:.1} ± {:.1} (σ {:.4})",
+                    g.rating, g.deviation, g.volatility
+                ))
+            }
+            Ranking::BradleyTerry => Some(format!("strength {:.4}", entry.strength())),
+            Ranking::Rank => entry.rank.map(|r| format!("#{}", r)),
+        }
+    }
+}
+
+impl fmt::Display for Ranking {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ranking::Percentage => write!(f, "percentage"),
+            Ranking::Elo => write!(f, "elo"),
+            Ranking::Glicko => write!(f, "glicko"),
+            Ranking::BradleyTerry => write!(f, "bt"),
+            Ranking::Rank => write!(f, "rank"),
+        }
+    }
+}
+
+impl TryFrom<&str> for Ranking {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "percentage" => Ok(Ranking::Percentage),
+            "elo" => Ok(Ranking::Elo),
+            "glicko" => Ok(Ranking::Glicko),
+            "bt" => Ok(Ranking::BradleyTerry),
+            "rank" => Ok(Ranking::Rank),
+            _ => Err(Error::ArgError),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        BradleyTerryConfig, EloConfig, Glicko, GlickoConfig, RatingConfig, RatingOverrides,
+    };
+
+    #[test]
+    fn rating_overrides_apply() {
+        let mut config = RatingConfig::default();
+        config.elo.k_factor = 16.0;
+        config.elo.initial = 1000.0;
+
+        let overrides = RatingOverrides {
+            initial_elo: Some(1200.0),
+            ..Default::default()
+        };
+        overrides.apply(&mut config);
+
+        assert_eq!(config.elo.k_factor, 16.0);
+        assert_eq!(config.elo.initial, 1200.0);
+        assert_eq!(config.bradley_terry, BradleyTerryConfig::default());
+    }
+
+    #[test]
+    fn elo_expected() {
+        assert_eq!(EloConfig::expected(1500.0, 1500.0), 0.5);
+        assert!((EloConfig::expected(1900.0, 1500.0) - 0.9090909090909091).abs() < 1e-12);
+        assert!(
+            (EloConfig::expected(1500.0, 1700.0) + EloConfig::expected(1700.0, 1500.0) - 1.0).abs()
+                < 1e-12
+        );
+    }
+
+    #[test]
+    fn elo_delta() {
+        let config = EloConfig::default();
+
+        assert_eq!(config.delta(1500.0, 1500.0, 1.0), 16.0);
+        assert_eq!(config.delta(1500.0, 1500.0, 0.0), -16.0);
+        assert!(config.delta(1900.0, 1500.0, 1.0) < 3.0);
+    }
+
+    #[test]
+    fn glicko_update() {
+        // Example from Glickman's "Example of the Glicko-2 system"
+        let config = GlickoConfig::default();
+        let player = Glicko {
+            rating: 1500.0,
+            deviation: 200.0,
+            volatility: 0.06,
+        };
+        let games = [
+            (
+                Glicko {
+                    rating: 1400.0,
+                    deviation: 30.0,
+                    volatility: 0.06,
+                },
+                1.0,
+            ),
+            (
+                Glicko {
+                    rating: 1550.0,
+                    deviation: 100.0,
+                    volatility: 0.06,
+                },
+                0.0,
+            ),
+            (
+                Glicko {
+                    rating: 1700.0,
+                    deviation: 300.0,
+                    volatility: 0.06,
+                },
+                0.0,
+            ),
+        ];
+
+        let g = config.update(player, &games);
+
+        assert!((g.rating - 1464.06).abs() < 0.01);
+        assert!((g.deviation - 151.52).abs() < 0.01);
+        assert!((g.volatility - 0.05999).abs() < 0.00001);
+    }
+
+    #[test]
+    fn glicko_information() {
+        let config = GlickoConfig::default();
+        let certain = Glicko {
+            deviation: 50.0,
+            ..config.initial
+        };
+        let strong = Glicko {
+            rating: 2100.0,
+            ..config.initial
+        };
+
+        let close = config.initial.information(&config.initial);
+
+        assert!(close > 0.0);
+        assert!(close > certain.information(&certain));
+        assert!(close > config.initial.information(&strong));
+        assert_eq!(
+            config.initial.information(&strong),
+            strong.information(&config.initial)
+        );
+    }
+
+    #[test]
+    fn glicko_idle() {
+        let config = GlickoConfig::default();
+        let player = Glicko {
+            rating: 1600.0,
+            deviation: 100.0,
+            volatility: 0.06,
+        };
+
+        let g = config.idle(player);
+
+        assert_eq!(g.rating, 1600.0);
+        assert!(g.deviation > 100.0);
+        assert_eq!(config.idle(config.initial), config.initial);
+    }
+
+    #[test]
+    fn bradley_terry_fit() {
+        let config = BradleyTerryConfig::default();
+        // 0 beats 1 twice, 1 beats 2 twice, 2 beats 0 once, 3 never played
+        let results = [
+            (0, 1, 1.0),
+            (0, 1, 1.0),
+            (1, 2, 1.0),
+            (1, 2, 1.0),
+            (2, 0, 1.0),
+        ];
+
+        let p = config.fit(4, &results);
+
+        assert!(p[0] > p[1] && p[1] > p[2]);
+        assert!((p[3] - 1.0).abs() < 1e-6);
+        assert!(p.iter().all(|p| p.is_finite() && *p > 0.0));
+    }
+
+    #[test]
+    fn bradley_terry_fit_unbeaten() {
+        let config = BradleyTerryConfig::default();
+        let results = [(0, 1, 1.0), (0, 1, 1.0), (0, 1, 1.0)];
+
+        let p = config.fit(2, &results);
+
+        assert!(p[0].is_finite() && p[1] > 0.0);
+        assert!(p[0] > 1.0 && p[1] < 1.0);
+        // Symmetric results give reciprocal strengths
+        assert!((p[0] * p[1] - 1.0).abs() < 1e-6);
+    }
+}
@@ -1,14 +1,23 @@
+use crate::bracket::Bracket;
 use crate::error::Error;
+use crate::history::{now, Outcome, PairWeights, Record, RepeatPolicy, Snapshot};
+use crate::rating::{EloConfig, Glicko, GlickoConfig, Ranking, RatingConfig, RatingPeriod};
+use crate::sort::MergeSort;
+use crate::storage::{Backend, Output, Stored};
+use crate::swiss::{Swiss, SWISS};
+use rand::distributions::{Distribution, WeightedIndex};
 use rand::Rng;
 use rand::{prelude::SliceRandom, rngs::ThreadRng};
 use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs::File;
 use std::{
     io,
     ops::{Index, IndexMut},
 };
 use std::{
-    io::{BufRead, BufReader, BufWriter},
+    io::{BufRead, BufReader},
     ops::Deref,
 };
 use std::{ops::DerefMut, path::Path};
@@ -21,8 +30,19 @@ pub struct RelEntry {
     pub wins: u32,
     #[serde(rename = "v", default)]
     pub votes: u32,
+    #[serde(rename = "d", default, skip_serializing_if = "is_zero")]
+    pub draws: u32,
     #[serde(rename = "l", default)]
     pub locked: bool,
+    #[serde(rename = "e", default, skip_serializing_if = "Option::is_none")]
+    pub elo: Option<f64>,
+    #[serde(rename = "g", default, skip_serializing_if = "Option::is_none")]
+    pub glicko: Option<Glicko>,
+    #[serde(rename = "b", default, skip_serializing_if = "Option::is_none")]
+    pub bt: Option<f64>,
+    /// Position given by a complete sort, 1 is best
+    #[serde(rename = "k", default, skip_serializing_if = "Option::is_none")]
+    pub rank: Option<u32>,
 }
 
 impl RelEntry {
@@ -31,27 +51,69 @@ impl RelEntry {
             name,
             wins,
             votes,
+            draws: 0,
             locked: false,
+            elo: None,
+            glicko: None,
+            bt: None,
+            rank: None,
         }
     }
 
+    /// Whether the entry has nothing but its name
+    pub fn is_new(&self) -> bool {
+        self.wins == 0
+            && self.votes == 0
+            && self.draws == 0
+            && !self.locked
+            && self.elo.is_none()
+            && self.glicko.is_none()
+            && self.bt.is_none()
+            && self.rank.is_none()
+    }
+
     pub fn reset(&mut self) {
         self.wins = 0;
         self.votes = 0;
+        self.draws = 0;
         self.locked = false;
+        self.elo = None;
+        self.glicko = None;
+        self.bt = None;
+        self.rank = None;
     }
 
+    /// Share of won votes, draws count as half a win
     pub fn percentage(&self) -> f64 {
-        f64::from(self.wins) * 100.0 / f64::from(self.votes)
+        (f64::from(self.wins) + f64::from(self.draws) / 2.0) * 100.0 / f64::from(self.votes)
     }
 
     pub fn compare_percentage(&self, other: &RelEntry) -> Ordering {
         // TODO NaN should be handled different
-        let ap = self.wins * other.votes;
-        let bp = other.wins * self.votes;
+        let ap = (2 * u64::from(self.wins) + u64::from(self.draws)) * u64::from(other.votes);
+        let bp = (2 * u64::from(other.wins) + u64::from(other.draws)) * u64::from(self.votes);
 
         ap.cmp(&bp)
     }
+
+    /// Elo rating, unrated entries start at the configured initial rating
+    pub fn elo_rating(&self, config: &EloConfig) -> f64 {
+        self.elo.unwrap_or(config.initial)
+    }
+
+    /// Glicko-2 rating, unrated entries start at the configured initial rating
+    pub fn glicko_rating(&self, config: &GlickoConfig) -> Glicko {
+        self.glicko.unwrap_or(config.initial)
+    }
+
+    /// Bradley–Terry strength, 1 if it was never fitted
+    pub fn strength(&self) -> f64 {
+        self.bt.unwrap_or(1.0)
+    }
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
 }
 
 impl PartialEq for RelEntry {
@@ -60,13 +122,15 @@ impl PartialEq for RelEntry {
     }
 }
 
-impl ToString for RelEntry {
-    fn to_string(&self) -> String {
-        format!(
-            "{} - {}/{} - {}%{}",
-            self.name,
-            self.wins,
-            self.votes,
+impl fmt::Display for RelEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} - {}/{}", self.name, self.wins, self.votes)?;
+        if self.draws > 0 {
+            write!(f, " ({} draws)", self.draws)?;
+        }
+        write!(
+            f,
+            " - {}%{}",
             self.percentage(),
             if self.locked { " [L]" } else { "" }
         )
@@ -79,24 +143,86 @@ impl From<String> for RelEntry {
     }
 }
 
+/// Version of the list layout written by `RelVec::save`
+pub const VERSION: u32 = 1;
+
+/// Information about a list, kept in its file
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Meta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Unix time the list was created, unknown for old files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<u64>,
+}
+
+impl Meta {
+    fn new() -> Self {
+        Self {
+            title: None,
+            created: Some(now()),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct RelVec {
     pub inner: Vec<RelEntry>,
+    pub history: Vec<Record>,
+    /// Unfinished interactive sort
+    pub sort: Option<MergeSort>,
+    /// Running Swiss-system tournament
+    pub swiss: Option<Swiss>,
+    /// Unfinished elimination bracket
+    pub bracket: Option<Bracket>,
+    /// The list as it was loaded from or saved to a database
+    pub stored: Option<Stored>,
+    pub meta: Meta,
     rng: ThreadRng,
+    pub config: RatingConfig,
+    /// Whether pair strategies may offer pairs that were compared before
+    pub repeat: RepeatPolicy,
+    /// Voter new decisions are recorded for
+    pub voter: Option<String>,
+    /// Weights of pairs by the repeat policy, kept up to date by `decide`
+    weights: Option<PairWeights>,
+    period: RatingPeriod,
 }
 
 impl RelVec {
     pub fn new() -> Self {
         Self {
             inner: Vec::new(),
+            history: Vec::new(),
+            sort: None,
+            swiss: None,
+            bracket: None,
+            stored: None,
+            meta: Meta::new(),
             rng: rand::thread_rng(),
+            config: RatingConfig::default(),
+            repeat: RepeatPolicy::default(),
+            voter: None,
+            weights: None,
+            period: RatingPeriod::default(),
         }
     }
 
     pub fn create(names: Vec<String>) -> Self {
         Self {
             inner: names.into_iter().map(|s| RelEntry::new(s, 0, 0)).collect(),
+            history: Vec::new(),
+            sort: None,
+            swiss: None,
+            bracket: None,
+            stored: None,
+            meta: Meta::new(),
             rng: rand::thread_rng(),
+            config: RatingConfig::default(),
+            repeat: RepeatPolicy::default(),
+            voter: None,
+            weights: None,
+            period: RatingPeriod::default(),
         }
     }
 
@@ -109,26 +235,38 @@ impl RelVec {
                 .lines()
                 .map(|r| r.map(|s| RelEntry::new(s, 0, 0)))
                 .collect::<Result<Vec<RelEntry>, io::Error>>()?,
+            history: Vec::new(),
+            sort: None,
+            swiss: None,
+            bracket: None,
+            stored: None,
+            meta: Meta::new(),
             rng: rand::thread_rng(),
+            config: RatingConfig::default(),
+            repeat: RepeatPolicy::default(),
+            voter: None,
+            weights: None,
+            period: RatingPeriod::default(),
         })
     }
 
+    /// Reads a list from a JSON file or an SQLite database
     pub fn load<P: AsRef<Path>>(file: P) -> Result<Self, Error> {
-        let f = File::open(file)?;
-        let reader = BufReader::new(f);
-
-        Ok(Self {
-            inner: serde_json::from_reader(reader)?,
-            rng: rand::thread_rng(),
-        })
+        let file = file.as_ref();
+        Backend::of_file(file)?.storage()?.load(file)
     }
 
-    pub fn save<P: AsRef<Path>>(&self, file: P) -> Result<(), Error> {
-        let f = File::create(file)?;
-        let writer = BufWriter::new(f);
-
-        serde_json::to_writer(writer, &self.inner)?;
-        Ok(())
+    /// Writes the list where a command saves it, in the current layout.
+    /// Older files are upgraded. The kind of file is the given one, else that
+    /// of the existing file.
+    pub fn save_to(&mut self, output: Output) -> Result<(), Error> {
+        let file = Path::new(output.path);
+        match output.backend {
+            Some(backend) => backend,
+            None => Backend::of_file(file)?,
+        }
+        .storage()?
+        .save(self, file, output.backups)
     }
 
     pub fn add(&mut self, name: String) {
@@ -143,6 +281,340 @@ impl RelVec {
         self.sort_by(|a: &RelEntry, b: &RelEntry| a.compare_percentage(b).reverse())
     }
 
+    /// Sorts by the conservative Glicko-2 estimate (rating minus two deviations)
+    pub fn sort_conservative(&mut self) {
+        let config = self.config.glicko;
+
+        self.sort_by(|a: &RelEntry, b: &RelEntry| {
+            let a = a.glicko_rating(&config).conservative();
+            let b = b.glicko_rating(&config).conservative();
+
+            b.partial_cmp(&a).unwrap_or(Ordering::Equal)
+        })
+    }
+
+    pub fn sort_ranking(&mut self, ranking: Ranking) {
+        let config = self.config;
+
+        match ranking {
+            Ranking::Percentage => self.sort_percentage(),
+            Ranking::Glicko => self.sort_conservative(),
+            _ => {
+                self.sort_by(|a: &RelEntry, b: &RelEntry| ranking.compare(a, b, &config).reverse())
+            }
+        }
+    }
+
+    /// Applies a decision between two entries and records it in the history.
+    /// `strategy` names what chose the pair.
+    pub fn decide(&mut self, a: usize, b: usize, outcome: Outcome, strategy: &str) {
+        self.decide_by(a, b, outcome, 1, strategy)
+    }
+
+    /// Like `decide`, with the margin the winner was better by. It weighs the
+    /// decision in the rating models, the counters only see one vote.
+    pub fn decide_by(&mut self, a: usize, b: usize, outcome: Outcome, margin: u32, strategy: &str) {
+        let mut record = Record::new(
+            self[a].name.clone(),
+            self[b].name.clone(),
+            outcome,
+            strategy.to_owned(),
+        );
+        record.margin = margin.max(1);
+        record.voter = self.voter.clone();
+
+        match outcome {
+            Outcome::First | Outcome::Second | Outcome::Draw => {
+                record.previous = Some([Snapshot::of(&self[a]), Snapshot::of(&self[b])])
+                    .filter(|p| p.iter().any(|s| !s.is_empty()));
+                self.play(a, b, outcome, record.margin);
+                if let (Some(swiss), Some(score)) = (&mut self.swiss, outcome.score()) {
+                    if strategy == SWISS {
+                        swiss.play(&record.a, &record.b, score);
+                    }
+                }
+            }
+            Outcome::Skip => {}
+            Outcome::RemoveFirst => record.removed = Some((a, self.inner.remove(a))),
+            Outcome::RemoveSecond => record.removed = Some((b, self.inner.remove(b))),
+        }
+
+        if let Some(weights) = &mut self.weights {
+            weights.push(record.compared());
+        }
+        self.history.push(record);
+    }
+
+    /// Reverts the last recorded decision and returns it
+    pub fn undo(&mut self) -> Option<Record> {
+        let record = self.history.pop()?;
+        self.weights = None;
+        if let Some(stored) = &mut self.stored {
+            stored.history = stored.history.min(self.history.len());
+        }
+
+        if let Some((i, e)) = &record.removed {
+            let i = (*i).min(self.len());
+            let mut e = e.clone();
+            if e.name.is_empty() {
+                e.name = match record.outcome {
+                    Outcome::RemoveFirst => record.a.clone(),
+                    _ => record.b.clone(),
+                };
+            }
+            self.inner.insert(i, e);
+        }
+        if let Some(score) = record.score() {
+            if let Some(swiss) = self.swiss.as_mut().filter(|_| record.strategy == SWISS) {
+                swiss.unplay(&record.a, &record.b);
+            }
+            for (name, score) in [(&record.a, score), (&record.b, 1.0 - score)] {
+                if let Some(i) = self.position(name) {
+                    let e = &mut self[i];
+                    e.votes = e.votes.saturating_sub(1);
+                    match score.partial_cmp(&0.5) {
+                        Some(Ordering::Greater) => e.wins = e.wins.saturating_sub(1),
+                        Some(Ordering::Equal) => e.draws = e.draws.saturating_sub(1),
+                        _ => {}
+                    }
+                }
+            }
+            for _ in 0..record.margin {
+                self.period.remove(&record.a, &record.b);
+            }
+        }
+        if record.score().is_some() {
+            let [a, b] = record.previous.unwrap_or_default();
+            if let Some(i) = self.position(&record.a) {
+                a.restore(&mut self[i]);
+            }
+            if let Some(i) = self.position(&record.b) {
+                b.restore(&mut self[i]);
+            }
+        }
+
+        Some(record)
+    }
+
+    /// Applies an undone decision again
+    pub fn redo(&mut self, record: &Record) -> Option<(usize, usize)> {
+        let a = self.position(&record.a)?;
+        let b = self.position(&record.b)?;
+
+        self.decide_by(a, b, record.outcome, record.margin, &record.strategy);
+        Some((a, b))
+    }
+
+    /// Names of everybody who voted, in the order of their first decision
+    pub fn voters(&self) -> Vec<String> {
+        let mut voters: Vec<String> = Vec::new();
+
+        for v in self.history.iter().filter_map(|r| r.voter.as_ref()) {
+            if !voters.contains(v) {
+                voters.push(v.clone());
+            }
+        }
+
+        voters
+    }
+
+    /// The list as if only the given voter had voted. Counters and ratings are
+    /// computed again from their recorded decisions.
+    pub fn of_voter(&self, voter: &str) -> Self {
+        let mut rv = Self {
+            inner: self.inner.clone(),
+            meta: self.meta.clone(),
+            config: self.config,
+            ..Self::new()
+        };
+        for e in rv.iter_mut() {
+            let (locked, rank) = (e.locked, e.rank);
+            e.reset();
+            e.locked = locked;
+            e.rank = rank;
+        }
+
+        for r in self
+            .history
+            .iter()
+            .filter(|r| r.voter.as_deref() == Some(voter))
+        {
+            if let (Some(a), Some(b)) = (rv.position(&r.a), rv.position(&r.b)) {
+                rv.play(a, b, r.outcome, r.margin);
+            }
+            rv.history.push(r.clone());
+        }
+        rv.close_period();
+
+        if self.iter().any(|e| e.bt.is_some()) {
+            rv.fit_bradley_terry();
+        }
+
+        rv
+    }
+
+    /// Index of the entry with the given name. Names are unique, commands
+    /// that add entries refuse names that are already in the list.
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.iter().position(|e| e.name == name)
+    }
+
+    /// First name that is in the list more than once
+    pub fn duplicate(&self) -> Option<&str> {
+        let mut names = HashSet::new();
+        self.iter()
+            .map(|e| e.name.as_str())
+            .find(|name| !names.insert(*name))
+    }
+
+    /// Counts a vote and updates the ratings of both entries, weighted by `margin`
+    fn play(&mut self, a: usize, b: usize, outcome: Outcome, margin: u32) {
+        let score = match outcome.score() {
+            Some(score) => score,
+            None => return,
+        };
+
+        self[a].votes += 1;
+        self[b].votes += 1;
+        match score.partial_cmp(&0.5) {
+            Some(Ordering::Greater) => self[a].wins += 1,
+            Some(Ordering::Less) => self[b].wins += 1,
+            _ => {
+                self[a].draws += 1;
+                self[b].draws += 1;
+            }
+        }
+
+        let config = self.config.elo;
+        let ra = self[a].elo_rating(&config);
+        let rb = self[b].elo_rating(&config);
+        let delta = config.delta(ra, rb, score) * f64::from(margin);
+        self[a].elo = Some(ra + delta);
+        self[b].elo = Some(rb - delta);
+
+        let config = self.config.glicko;
+        // A clear decision counts as several games
+        for _ in 0..margin {
+            self.period
+                .add(&self.inner[a], &self.inner[b], score, &config);
+        }
+        self[a].glicko = self.period.rate(&self[a].name, &config);
+        self[b].glicko = self.period.rate(&self[b].name, &config);
+    }
+
+    /// Ranks the named entries in the given order, all others lose their rank
+    pub fn set_ranks(&mut self, order: &[String]) {
+        for e in self.iter_mut() {
+            e.rank = order
+                .iter()
+                .position(|n| *n == e.name)
+                .map(|i| i as u32 + 1);
+        }
+    }
+
+    /// Gives an entry Elo, Glicko-2 and Bradley–Terry ratings between two
+    /// other entries, `above` being the better one. A missing neighbour stands
+    /// for the top or the bottom of the list. Wins and votes stay as they are,
+    /// they only come from recorded decisions.
+    pub fn seed(&mut self, i: usize, above: Option<usize>, below: Option<usize>) {
+        let elo = self.config.elo;
+        let rating = |j: usize| self[j].elo_rating(&elo);
+        if let Some(r) = between(above.map(rating), below.map(rating), elo.k_factor) {
+            self[i].elo = Some(r);
+        }
+
+        // Nothing is known about the entry, so it keeps the initial deviation
+        let glicko = self.config.glicko;
+        let rating = |j: usize| self[j].glicko_rating(&glicko).rating;
+        let step = glicko.initial.deviation;
+        if let Some(r) = between(above.map(rating), below.map(rating), step) {
+            self[i].glicko = Some(Glicko {
+                rating: r,
+                ..glicko.initial
+            });
+        }
+
+        // Strengths are only seeded once they were fitted, halfway on the log
+        // scale
+        if above.or(below).is_some_and(|j| self[j].bt.is_some()) {
+            let strength = |j: usize| self[j].strength().ln();
+            self[i].bt = between(above.map(strength), below.map(strength), 2f64.ln()).map(f64::exp);
+        }
+    }
+
+    /// Fits Bradley–Terry strengths to all recorded decisions
+    pub fn fit_bradley_terry(&mut self) {
+        let mut index = HashMap::new();
+        for (i, e) in self.iter().enumerate() {
+            index.entry(e.name.as_str()).or_insert(i);
+        }
+
+        let results: Vec<(usize, usize, f64)> = self
+            .history
+            .iter()
+            .filter_map(|r| {
+                Some((
+                    *index.get(r.a.as_str())?,
+                    *index.get(r.b.as_str())?,
+                    r.score()?,
+                    f64::from(r.margin),
+                ))
+            })
+            .flat_map(|(a, b, score, m)| vec![(a, b, score * m), (b, a, (1.0 - score) * m)])
+            .filter(|(_, _, weight)| *weight > 0.0)
+            .collect();
+        let strengths = self.config.bradley_terry.fit(self.len(), &results);
+
+        for (e, p) in self.iter_mut().zip(strengths) {
+            e.bt = Some(p);
+        }
+    }
+
+    /// Wins of every entry (row) against every other one (column), weighted
+    /// by margin. A draw counts as half a win for both.
+    pub fn pairwise(&self) -> Vec<Vec<f64>> {
+        let entries: Vec<usize> = (0..self.len()).collect();
+        self.pairwise_of(&entries)
+    }
+
+    /// Like `pairwise` for some of the entries, rows and columns are in the
+    /// order of `entries`
+    pub fn pairwise_of(&self, entries: &[usize]) -> Vec<Vec<f64>> {
+        let mut index = HashMap::new();
+        for (i, &e) in entries.iter().enumerate() {
+            index.entry(self[e].name.as_str()).or_insert(i);
+        }
+
+        let mut matrix = vec![vec![0.0; entries.len()]; entries.len()];
+        for r in &self.history {
+            if let (Some(&a), Some(&b), Some(score)) =
+                (index.get(r.a.as_str()), index.get(r.b.as_str()), r.score())
+            {
+                let m = f64::from(r.margin);
+                matrix[a][b] += score * m;
+                matrix[b][a] += (1.0 - score) * m;
+            }
+        }
+
+        matrix
+    }
+
+    /// Ends the current Glicko-2 rating period. Entries that weren't voted on
+    /// in this period become less certain.
+    pub fn close_period(&mut self) {
+        if self.period.is_empty() {
+            return;
+        }
+
+        let config = self.config.glicko;
+        let period = std::mem::take(&mut self.period);
+        for e in self.iter_mut().filter(|e| !period.contains(&e.name)) {
+            if let Some(g) = e.glicko {
+                e.glicko = Some(config.idle(g));
+            }
+        }
+    }
+
     pub fn reduced(&self) -> Vec<usize> {
         self.inner
             .iter()
@@ -153,7 +625,7 @@ impl RelVec {
     }
 
     pub fn min_votes(&mut self) -> Vec<usize> {
-        let mut min = u32::max_value();
+        let mut min = u32::MAX;
         let mut v = Vec::new();
 
         for i in 0..self.len() {
@@ -176,6 +648,53 @@ impl RelVec {
         v
     }
 
+    /// Builds the weights of pairs according to the repeat policy unless they
+    /// are up to date
+    fn update_weights(&mut self) {
+        let names: Vec<&str> = self
+            .inner
+            .iter()
+            .filter(|e| !e.locked)
+            .map(|e| e.name.as_str())
+            .collect();
+
+        let current = self
+            .weights
+            .as_ref()
+            .is_some_and(|w| w.is_current(self.history.len(), names.len(), self.repeat));
+        if !current {
+            self.weights = Some(PairWeights::new(&self.history, &names, self.repeat));
+        }
+    }
+
+    /// Draws pairs until one passes the repeat policy. After too many rejected
+    /// pairs one is chosen from all `candidates` by weight.
+    fn pick<D, C>(&mut self, mut draw: D, candidates: C) -> Option<(usize, usize)>
+    where
+        D: FnMut(&mut ThreadRng) -> (usize, usize),
+        C: FnOnce() -> Vec<(usize, usize)>,
+    {
+        self.update_weights();
+        let weights = self.weights.as_ref()?;
+
+        for _ in 0..MAX_DRAWS {
+            let (a, b) = draw(&mut self.rng);
+            let w = weights.weight(&self[a].name, &self[b].name);
+            if w >= 1.0 || (w > 0.0 && self.rng.gen_bool(w)) {
+                return Some((a, b));
+            }
+        }
+
+        let candidates: Vec<((usize, usize), f64)> = candidates()
+            .into_iter()
+            .map(|(a, b)| ((a, b), weights.weight(&self[a].name, &self[b].name)))
+            .filter(|(_, w)| *w > 0.0)
+            .collect();
+        let index = WeightedIndex::new(candidates.iter().map(|(_, w)| *w)).ok()?;
+
+        Some(candidates[index.sample(&mut self.rng)].0)
+    }
+
     pub fn random_pair(&mut self) -> Option<(usize, usize)> {
         let reduced: Vec<usize> = self.reduced();
 
@@ -183,13 +702,18 @@ impl RelVec {
             return None;
         }
 
-        let i1 = self.rng.gen_range(0..reduced.len());
-        let i2 = self.rng.gen_range(0..(reduced.len() - 1));
-        if i2 >= i1 {
-            Some((reduced[i1], reduced[i2 + 1]))
-        } else {
-            Some((reduced[i1], reduced[i2]))
-        }
+        self.pick(
+            |rng| {
+                let i1 = rng.gen_range(0..reduced.len());
+                let i2 = rng.gen_range(0..(reduced.len() - 1));
+                if i2 >= i1 {
+                    (reduced[i1], reduced[i2 + 1])
+                } else {
+                    (reduced[i1], reduced[i2])
+                }
+            },
+            || pairs(&reduced, &reduced),
+        )
     }
 
     pub fn min_pair(&mut self) -> Option<(usize, usize)> {
@@ -199,16 +723,21 @@ impl RelVec {
             return None;
         }
 
-        let i1 = mins[self.rng.gen_range(0..mins.len())];
-        let i2 = reduced[self.rng.gen_range(0..(reduced.len() - 1))];
-        if i2 >= i1 {
-            Some((i1, i2 + 1))
-        } else {
-            Some((i1, i2))
-        }
+        self.pick(
+            |rng| {
+                let i1 = mins[rng.gen_range(0..mins.len())];
+                let i2 = reduced[rng.gen_range(0..(reduced.len() - 1))];
+                if i2 >= i1 {
+                    (i1, i2 + 1)
+                } else {
+                    (i1, i2)
+                }
+            },
+            || pairs(&mins, &reduced),
+        )
     }
 
-    pub fn equal_pair(&mut self) -> Option<(usize, usize)> {
+    pub fn equal_pair(&mut self, ranking: Ranking) -> Option<(usize, usize)> {
         let mut reduced = self.reduced();
 
         if reduced.len() < 2 {
@@ -216,20 +745,32 @@ impl RelVec {
         }
 
         reduced.shuffle(&mut self.rng);
+        self.update_weights();
+        let weights = self.weights.as_ref()?;
+        let mut best = None;
 
         for i1 in 0..reduced.len() {
             for i2 in i1 + 1..reduced.len() {
-                if (self[reduced[i2]].percentage() - self[reduced[i1]].percentage()).abs()
+                if (ranking.value(&self[reduced[i2]], &self.config)
+                    - ranking.value(&self[reduced[i1]], &self.config))
+                .abs()
                     < f64::EPSILON
                 {
-                    return Some((reduced[i1], reduced[i2]));
+                    let w = weights.weight(&self[reduced[i1]].name, &self[reduced[i2]].name);
+                    if w >= 1.0 {
+                        return Some((reduced[i1], reduced[i2]));
+                    }
+                    if w > best.map_or(0.0, |(_, _, b)| b) {
+                        best = Some((reduced[i1], reduced[i2], w));
+                    }
                 }
             }
         }
-        None
+
+        best.map(|(a, b, _)| (a, b))
     }
 
-    pub fn nearest_pair(&mut self) -> Option<(usize, usize)> {
+    pub fn nearest_pair(&mut self, ranking: Ranking) -> Option<(usize, usize)> {
         let mut reduced = self.reduced();
 
         if reduced.len() < 2 {
@@ -237,12 +778,22 @@ impl RelVec {
         }
 
         reduced.shuffle(&mut self.rng);
+        self.update_weights();
+        let weights = self.weights.as_ref()?;
 
         let mut min = None;
 
         for i1 in 0..reduced.len() {
             for i2 in i1 + 1..reduced.len() {
-                let d2 = (self[reduced[i2]].percentage() - self[reduced[i1]].percentage()).abs();
+                let w = weights.weight(&self[reduced[i1]].name, &self[reduced[i2]].name);
+                if w <= 0.0 {
+                    continue;
+                }
+                // Pairs that were compared before look farther apart
+                let d2 = (ranking.value(&self[reduced[i2]], &self.config)
+                    - ranking.value(&self[reduced[i1]], &self.config))
+                .abs()
+                    / w;
                 match min {
                     Some((_, _, d)) => {
                         if d2 < d {
@@ -259,9 +810,100 @@ impl RelVec {
         min.map(|(a, b, _)| (reduced[a], reduced[b]))
     }
 
-    pub fn min_equal_pair(&mut self) -> Option<(usize, usize)> {
+    /// Pair whose comparison is expected to make the Glicko-2 ratings most
+    /// certain. Only entries close in rating are compared, unless the repeat
+    /// policy rules out all of them.
+    pub fn informative_pair(&mut self) -> Option<(usize, usize)> {
+        let mut reduced = self.reduced();
+
+        if reduced.len() < 2 {
+            return None;
+        }
+
+        reduced.shuffle(&mut self.rng);
+        self.update_weights();
+        let weights = self.weights.as_ref()?;
+        let mut entries: Vec<(usize, Glicko, Option<usize>)> = reduced
+            .iter()
+            .map(|&i| {
+                let rating = self[i].glicko_rating(&self.config.glicko);
+                (i, rating, weights.id_of(&self[i].name))
+            })
+            .collect();
+        entries.sort_by(|a, b| a.1.rating.total_cmp(&b.1.rating));
+
+        let mut max = None;
+
+        for neighbours in [NEIGHBOURS, entries.len()] {
+            for (i1, (a, ra, ia)) in entries.iter().enumerate() {
+                for (b, rb, ib) in entries.iter().skip(i1 + 1).take(neighbours) {
+                    let gain = ra.information(rb) * weights.weight_of(*ia, *ib);
+                    if gain > max.map_or(0.0, |(_, _, m)| m) {
+                        max = Some((*a, *b, gain));
+                    }
+                }
+            }
+            if max.is_some() || neighbours >= entries.len() {
+                break;
+            }
+        }
+
+        max.map(|(a, b, _)| (a, b))
+    }
+
+    /// Next pair of the running Swiss-system tournament. A new round starts
+    /// once every pair of the current one was decided, skipped pairs are
+    /// asked again after the others.
+    pub fn swiss_pair(&mut self) -> Option<(usize, usize)> {
+        let mut names: Vec<String> = self
+            .iter()
+            .filter(|e| !e.locked)
+            .map(|e| e.name.clone())
+            .collect();
+
+        if names.len() < 2 {
+            return None;
+        }
+
+        let mut swiss = self.swiss.take().unwrap_or_default();
+        let mut pair = None;
+
+        for new_round in [false, true] {
+            if new_round {
+                names.shuffle(&mut self.rng);
+                if !swiss.next_round(&names, self.repeat) {
+                    break;
+                }
+            }
+
+            pair = swiss
+                .pending()
+                .into_iter()
+                .filter_map(|(a, b)| {
+                    let i = self.position(a).filter(|&i| !self[i].locked)?;
+                    let j = self.position(b).filter(|&j| !self[j].locked)?;
+                    let skips = self
+                        .history
+                        .iter()
+                        .filter(|r| r.outcome == Outcome::Skip && r.strategy == SWISS)
+                        .filter(|r| (&r.a, &r.b) == (a, b) || (&r.a, &r.b) == (b, a))
+                        .count();
+                    Some((skips, (i, j)))
+                })
+                .min_by_key(|(skips, _)| *skips)
+                .map(|(_, pair)| pair);
+            if pair.is_some() {
+                break;
+            }
+        }
+
+        self.swiss = Some(swiss);
+        pair
+    }
+
+    pub fn min_equal_pair(&mut self, ranking: Ranking) -> Option<(usize, usize)> {
         if self.rng.gen_bool(0.5) {
-            match self.equal_pair() {
+            match self.equal_pair(ranking) {
                 Some((a, b)) => Some((a, b)),
                 None => self.min_pair(),
             }
@@ -271,6 +913,19 @@ impl RelVec {
     }
 }
 
+/// Attempts of a strategy to draw a pair allowed by the repeat policy
+const MAX_DRAWS: usize = 100;
+
+/// Entries above each entry in rating that `informative_pair` compares it to
+const NEIGHBOURS: usize = 8;
+
+/// All pairs of one entry of `a` and a different one of `b`
+fn pairs(a: &[usize], b: &[usize]) -> Vec<(usize, usize)> {
+    a.iter()
+        .flat_map(|&i| b.iter().filter(move |&&j| j != i).map(move |&j| (i, j)))
+        .collect()
+}
+
 impl PartialEq for RelVec {
     fn eq(&self, other: &Self) -> bool {
         self.inner == other.inner
@@ -317,6 +972,17 @@ impl IndexMut<usize> for RelVec {
     }
 }
 
+/// Rating halfway between those of two neighbours, or `step` beyond the only
+/// one
+fn between(above: Option<f64>, below: Option<f64>, step: f64) -> Option<f64> {
+    match (above, below) {
+        (Some(a), Some(b)) => Some((a + b) / 2.0),
+        (Some(a), None) => Some(a - step),
+        (None, Some(b)) => Some(b + step),
+        (None, None) => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{
@@ -325,7 +991,10 @@ mod tests {
         io::{BufWriter, Write},
     };
 
-    use super::{RelEntry, RelVec};
+    use super::{Meta, RelEntry, RelVec, VERSION};
+    use crate::error::Error;
+    use crate::history::{Outcome, Record, RepeatPolicy, CLEAR};
+    use crate::rating::{Ranking, RatingConfig, RatingPeriod};
 
     #[test]
     fn rel_entry_new() {
@@ -334,7 +1003,12 @@ mod tests {
                 name: "abc".to_owned(),
                 wins: 125132,
                 votes: 12551,
-                locked: false
+                draws: 0,
+                locked: false,
+                elo: None,
+                glicko: None,
+                bt: None,
+                rank: None,
             },
             RelEntry::new("abc".to_owned(), 125132, 12551)
         );
@@ -346,13 +1020,23 @@ mod tests {
             name: "abc".to_owned(),
             wins: 0,
             votes: 0,
+            draws: 0,
             locked: false,
+            elo: None,
+            glicko: None,
+            bt: None,
+            rank: None,
         };
         let mut b = RelEntry {
             name: "abc".to_owned(),
             wins: 125132,
             votes: 12551,
+            draws: 0,
             locked: true,
+            elo: None,
+            glicko: None,
+            bt: None,
+            rank: None,
         };
 
         b.reset();
@@ -384,6 +1068,20 @@ mod tests {
         assert_eq!(e.percentage(), 40.06410256410256);
     }
 
+    #[test]
+    fn rel_entry_percentage_draws() {
+        let mut e = RelEntry::new("abc".to_owned(), 1, 4);
+
+        e.draws = 2;
+
+        assert_eq!(e.percentage(), 50.0);
+        assert_eq!(e.to_string(), "abc - 1/4 (2 draws) - 50%");
+        assert_eq!(
+            e.compare_percentage(&RelEntry::new("abc".to_owned(), 1, 2)),
+            Ordering::Equal
+        );
+    }
+
     #[test]
     fn rel_entry_compare_percentage() {
         let mut a = RelEntry::new("abc".to_owned(), 0, 0);
@@ -415,13 +1113,23 @@ mod tests {
             name: "abc".to_owned(),
             wins: 125132,
             votes: 1263,
+            draws: 0,
             locked: false,
+            elo: None,
+            glicko: None,
+            bt: None,
+            rank: None,
         };
         let b = RelEntry {
             name: "abc".to_owned(),
             wins: 1251,
             votes: 1361621,
+            draws: 0,
             locked: false,
+            elo: None,
+            glicko: None,
+            bt: None,
+            rank: None,
         };
 
         assert_eq!(a, b);
@@ -433,7 +1141,12 @@ mod tests {
             name: "abc".to_owned(),
             wins: 12,
             votes: 36,
+            draws: 0,
             locked: false,
+            elo: None,
+            glicko: None,
+            bt: None,
+            rank: None,
         };
 
         assert_eq!(a.to_string(), "abc - 12/36 - 33.333333333333336%");
@@ -445,7 +1158,12 @@ mod tests {
             name: "abc".to_owned(),
             wins: 0,
             votes: 0,
+            draws: 0,
             locked: false,
+            elo: None,
+            glicko: None,
+            bt: None,
+            rank: None,
         };
         let b = "abc".to_owned().into();
 
@@ -456,7 +1174,18 @@ mod tests {
     fn rel_vec_new() {
         let a = RelVec {
             inner: Vec::new(),
+            history: Vec::new(),
+            sort: None,
+            swiss: None,
+            bracket: None,
+            stored: None,
+            meta: Meta::new(),
             rng: rand::thread_rng(),
+            config: RatingConfig::default(),
+            repeat: RepeatPolicy::default(),
+            voter: None,
+            weights: None,
+            period: RatingPeriod::default(),
         };
         let b = RelVec::new();
 
@@ -470,7 +1199,7 @@ mod tests {
                 RelEntry::new("abc".to_string(), 1251, 16162),
                 RelEntry::new("adsga".to_string(), 1251, 1236),
             ]),
-            rng: rand::thread_rng(),
+            ..RelVec::new()
         };
         let b = RelVec::create(Vec::from(["abc".to_string(), "adsga".to_string()]));
 
@@ -507,7 +1236,7 @@ mod tests {
 
         let a = RelVec {
             inner: [RelEntry::new("abc".to_owned(), 2, 3)].to_vec(),
-            rng: rand::thread_rng(),
+            ..RelVec::new()
         };
         let b = RelVec::load("_rel_vec_load.txt").unwrap();
 
@@ -518,10 +1247,11 @@ mod tests {
 
     #[test]
     fn rel_vec_save() {
-        let rv = RelVec::create(["abc".to_string()].to_vec());
-        rv.save("_rel_vec_save.txt").unwrap();
+        let mut rv = RelVec::create(["abc".to_string()].to_vec());
+        rv.meta.created = Some(1600000000);
+        rv.save_to("_rel_vec_save.txt".into()).unwrap();
 
-        let a = b"[{\"n\":\"abc\",\"w\":0,\"v\":0,\"l\":false}]";
+        let a = b"{\"version\":1,\"meta\":{\"created\":1600000000},\"entries\":[{\"n\":\"abc\",\"w\":0,\"v\":0,\"l\":false}]}";
         let b = fs::read("_rel_vec_save.txt").unwrap();
 
         fs::remove_file("_rel_vec_save.txt").unwrap();
@@ -529,11 +1259,121 @@ mod tests {
         assert_eq!(a, b.as_slice());
     }
 
+    #[test]
+    fn rel_vec_save_meta() {
+        let mut rv = RelVec::create(["a".to_string(), "b".to_string()].to_vec());
+        rv.meta.title = Some("Things".to_string());
+        rv.config.elo.k_factor = 16.0;
+        rv.save_to("_rel_vec_save_meta.txt".into()).unwrap();
+
+        let b = RelVec::load("_rel_vec_save_meta.txt").unwrap();
+
+        fs::remove_file("_rel_vec_save_meta.txt").unwrap();
+
+        assert_eq!(b.meta, rv.meta);
+        assert_eq!(b.config, rv.config);
+    }
+
+    #[test]
+    fn rel_vec_load_list() {
+        fs::write(
+            "_rel_vec_load_list.txt",
+            b"{\"inner\":[{\"n\":\"a\",\"w\":1,\"v\":1},{\"n\":\"b\",\"w\":0,\"v\":1}],\"history\":[{\"a\":\"a\",\"b\":\"b\",\"o\":\"1\",\"t\":0,\"s\":\"\"}]}",
+        )
+        .unwrap();
+
+        let rv = RelVec::load("_rel_vec_load_list.txt").unwrap();
+
+        fs::remove_file("_rel_vec_load_list.txt").unwrap();
+
+        assert_eq!(rv.len(), 2);
+        assert_eq!(rv.history.len(), 1);
+        assert_eq!(rv.meta, Meta::default());
+        assert_eq!(rv.config, RatingConfig::default());
+    }
+
+    #[test]
+    fn rel_vec_load_newer() {
+        fs::write(
+            "_rel_vec_load_newer.txt",
+            format!("{{\"version\":{},\"entries\":[]}}", VERSION + 1),
+        )
+        .unwrap();
+
+        let result = RelVec::load("_rel_vec_load_newer.txt");
+
+        fs::remove_file("_rel_vec_load_newer.txt").unwrap();
+
+        assert!(matches!(result, Err(Error::Version(v)) if v == VERSION + 1));
+    }
+
+    #[test]
+    fn rel_vec_save_history() {
+        let mut rv = RelVec::create(["a".to_string(), "b".to_string()].to_vec());
+        rv.decide(0, 1, Outcome::Second, "");
+        rv.save_to("_rel_vec_save_history.txt".into()).unwrap();
+
+        let b = RelVec::load("_rel_vec_save_history.txt").unwrap();
+
+        fs::remove_file("_rel_vec_save_history.txt").unwrap();
+
+        assert_eq!(rv, b);
+        assert_eq!(rv.history, b.history);
+    }
+
+    #[test]
+    fn rel_vec_save_history_compact() {
+        let mut rv = RelVec::create(["a", "b", "c"].iter().map(|s| s.to_string()).collect());
+        rv.decide(0, 1, Outcome::First, "");
+        rv.decide(2, 0, Outcome::RemoveFirst, "");
+        assert_eq!(rv.history[0].previous, None);
+        rv.save_to("_rel_vec_save_history_compact.txt".into())
+            .unwrap();
+
+        let content = fs::read_to_string("_rel_vec_save_history_compact.txt").unwrap();
+        let mut b = RelVec::load("_rel_vec_save_history_compact.txt").unwrap();
+        fs::remove_file("_rel_vec_save_history_compact.txt").unwrap();
+
+        // Both were unrated and c was never voted on
+        assert!(!content.contains("\"p\""));
+        assert!(content.contains("\"r\":2}"));
+
+        b.undo();
+        b.undo();
+        let names: Vec<&str> = b.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert!(b.iter().all(|e| e.votes == 0 && e.elo.is_none()));
+    }
+
+    #[test]
+    fn rel_vec_duplicate() {
+        let mut rv = RelVec::create(["a", "b"].iter().map(|s| s.to_string()).collect());
+        assert_eq!(rv.duplicate(), None);
+
+        rv.add("a".to_owned());
+        assert_eq!(rv.duplicate(), Some("a"));
+    }
+
+    #[test]
+    fn rel_vec_fit_bradley_terry() {
+        let mut rv = RelVec::create(["a".to_string(), "b".to_string(), "c".to_string()].to_vec());
+
+        rv.decide(0, 1, Outcome::First, "");
+        rv.decide(1, 2, Outcome::First, "");
+        rv.decide(0, 2, Outcome::First, "");
+        rv.fit_bradley_terry();
+        rv.sort_ranking(Ranking::BradleyTerry);
+
+        let names: Vec<&str> = rv.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert!(rv.iter().all(|e| e.bt.is_some()));
+    }
+
     #[test]
     fn rel_vec_add() {
         let mut rv = RelVec {
             inner: Vec::new(),
-            rng: rand::thread_rng(),
+            ..RelVec::new()
         };
 
         rv.add("abc".to_owned());
@@ -542,7 +1382,7 @@ mod tests {
             rv,
             RelVec {
                 inner: [RelEntry::new("abc".to_owned(), 0, 0)].to_vec(),
-                rng: rand::thread_rng()
+                ..RelVec::new()
             }
         )
     }
@@ -551,7 +1391,7 @@ mod tests {
     fn rel_vec_remove() {
         let mut rv = RelVec {
             inner: [RelEntry::new("abc".to_owned(), 0, 0)].to_vec(),
-            rng: rand::thread_rng(),
+            ..RelVec::new()
         };
 
         rv.remove(|entry| entry.name.len() == 3);
@@ -570,7 +1410,7 @@ mod tests {
                 RelEntry::new("abc".to_owned(), 0, 0),
             ]
             .to_vec(),
-            rng: rand::thread_rng(),
+            ..RelVec::new()
         };
 
         rv.sort_percentage();
@@ -586,11 +1426,280 @@ mod tests {
                     RelEntry::new("abc".to_owned(), 0, 0),
                 ]
                 .to_vec(),
-                rng: rand::thread_rng(),
+                ..RelVec::new()
             }
         );
     }
 
+    #[test]
+    fn rel_vec_sort_ranking_elo() {
+        let mut rv = RelVec::create(["a".to_string(), "b".to_string(), "c".to_string()].to_vec());
+
+        rv[0].elo = Some(1400.0);
+        rv[2].elo = Some(1600.0);
+        rv.sort_ranking(Ranking::Elo);
+
+        let names: Vec<&str> = rv.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["c", "b", "a"]);
+    }
+
+    #[test]
+    fn rel_vec_decide() {
+        let mut rv = RelVec::create(["a".to_string(), "b".to_string()].to_vec());
+
+        rv.decide(1, 0, Outcome::First, "");
+
+        assert_eq!((rv[0].wins, rv[0].votes), (0, 1));
+        assert_eq!((rv[1].wins, rv[1].votes), (1, 1));
+        assert_eq!(rv[0].elo, Some(1484.0));
+        assert_eq!(rv[1].elo, Some(1516.0));
+
+        rv.decide(0, 1, Outcome::First, "");
+
+        assert!(rv[0].elo.unwrap() > 1500.0);
+        assert!((rv[0].elo.unwrap() + rv[1].elo.unwrap() - 3000.0).abs() < 1e-9);
+        let history: Vec<(&str, &str, Outcome)> = rv
+            .history
+            .iter()
+            .map(|r| (r.a.as_str(), r.b.as_str(), r.outcome))
+            .collect();
+        assert_eq!(
+            history,
+            [("b", "a", Outcome::First), ("a", "b", Outcome::First)]
+        );
+    }
+
+    #[test]
+    fn rel_vec_decide_remove() {
+        let mut rv = RelVec::create(["a".to_string(), "b".to_string(), "c".to_string()].to_vec());
+
+        rv.decide(0, 1, Outcome::Skip, "random");
+        rv.decide(0, 2, Outcome::RemoveSecond, "random");
+
+        assert_eq!(
+            rv,
+            RelVec::create(["a".to_string(), "b".to_string()].to_vec())
+        );
+        assert_eq!(rv.history.len(), 2);
+        assert_eq!(rv.history[1].b, "c");
+        assert_eq!(rv.history[1].strategy, "random");
+        assert!(rv.history[1].time > 0);
+        assert_eq!(rv[0].votes + rv[1].votes, 0);
+    }
+
+    #[test]
+    fn rel_vec_undo() {
+        let mut rv = RelVec::create(["a".to_string(), "b".to_string(), "c".to_string()].to_vec());
+
+        rv.decide(0, 1, Outcome::First, "");
+        let before = rv.clone();
+        let entries: Vec<(u32, u32, Option<f64>)> =
+            rv.iter().map(|e| (e.wins, e.votes, e.elo)).collect();
+
+        rv.decide(1, 0, Outcome::First, "");
+        rv.decide(0, 2, Outcome::RemoveFirst, "");
+
+        assert_eq!(rv.len(), 2);
+        assert_eq!(rv.undo().unwrap().outcome, Outcome::RemoveFirst);
+        assert_eq!(rv.undo().unwrap().outcome, Outcome::First);
+
+        assert_eq!(rv, before);
+        assert_eq!(rv.history, before.history);
+        assert_eq!(
+            rv.iter()
+                .map(|e| (e.wins, e.votes, e.elo))
+                .collect::<Vec<_>>(),
+            entries
+        );
+        assert_eq!(rv[0].glicko, before[0].glicko);
+
+        rv.undo();
+        rv.undo();
+
+        assert!(rv.history.is_empty());
+        assert!(rv
+            .iter()
+            .all(|e| e.votes == 0 && e.elo.is_none() && e.glicko.is_none()));
+    }
+
+    #[test]
+    fn rel_vec_decide_draw() {
+        let mut rv = RelVec::create(["a".to_string(), "b".to_string()].to_vec());
+
+        rv.decide(0, 1, Outcome::Draw, "");
+
+        assert_eq!((rv[0].wins, rv[0].draws, rv[0].votes), (0, 1, 1));
+        assert_eq!((rv[1].wins, rv[1].draws, rv[1].votes), (0, 1, 1));
+        assert_eq!(rv[0].elo, Some(1500.0));
+        assert_eq!(rv[0].percentage(), 50.0);
+
+        rv[1].elo = Some(1600.0);
+        rv.decide(0, 1, Outcome::Draw, "");
+        assert!(rv[0].elo.unwrap() > 1500.0);
+
+        rv.undo();
+        rv.undo();
+
+        assert_eq!((rv[0].draws, rv[0].votes), (0, 0));
+        assert_eq!((rv[1].draws, rv[1].votes), (0, 0));
+    }
+
+    #[test]
+    fn rel_vec_decide_clear() {
+        let mut rv = RelVec::create(["a".to_string(), "b".to_string()].to_vec());
+        let mut normal = rv.clone();
+
+        rv.decide_by(0, 1, Outcome::First, CLEAR, "");
+        normal.decide(0, 1, Outcome::First, "");
+
+        assert_eq!((rv[0].wins, rv[0].votes), (1, 1));
+        assert_eq!(rv[0].elo, Some(1532.0));
+        assert!(rv[0].glicko.unwrap().rating > normal[0].glicko.unwrap().rating);
+        assert_eq!(rv.history[0].margin, CLEAR);
+
+        rv.fit_bradley_terry();
+        normal.fit_bradley_terry();
+        assert!(rv[0].bt.unwrap() > normal[0].bt.unwrap());
+
+        let record = rv.undo().unwrap();
+        assert_eq!(rv[0].elo, None);
+        rv.redo(&record);
+        assert_eq!(rv[0].elo, Some(1532.0));
+    }
+
+    #[test]
+    fn rel_vec_pairwise() {
+        let mut rv = RelVec::create(["a".to_string(), "b".to_string(), "c".to_string()].to_vec());
+
+        rv.decide(0, 1, Outcome::First, "");
+        rv.decide_by(2, 0, Outcome::First, CLEAR, "");
+        rv.decide(1, 2, Outcome::Draw, "");
+        rv.decide(0, 1, Outcome::Skip, "");
+
+        assert_eq!(
+            rv.pairwise(),
+            vec![
+                vec![0.0, 1.0, 0.0],
+                vec![0.0, 0.0, 0.5],
+                vec![2.0, 0.5, 0.0]
+            ]
+        );
+        assert_eq!(
+            rv.pairwise_of(&[2, 0]),
+            vec![vec![0.0, 2.0], vec![0.0, 0.0]]
+        );
+    }
+
+    #[test]
+    fn rel_vec_of_voter() {
+        let mut rv = RelVec::create(["a".to_string(), "b".to_string(), "c".to_string()].to_vec());
+
+        rv.voter = Some("x".to_string());
+        rv.decide(0, 1, Outcome::First, "");
+        rv.decide(0, 2, Outcome::First, "");
+        rv.voter = Some("y".to_string());
+        rv.decide(1, 0, Outcome::First, "");
+        rv.voter = None;
+        rv.decide(2, 1, Outcome::Draw, "");
+
+        assert_eq!(rv.voters(), ["x".to_string(), "y".to_string()].to_vec());
+        assert_eq!(rv.history[2].voter.as_deref(), Some("y"));
+
+        let x = rv.of_voter("x");
+        assert_eq!((x[0].wins, x[0].votes), (2, 2));
+        assert_eq!((x[1].wins, x[1].votes), (0, 1));
+        assert_eq!(x[2].draws, 0);
+        assert_eq!(x.history.len(), 2);
+        assert!(x[0].elo.unwrap() > 1500.0);
+
+        let y = rv.of_voter("y");
+        assert_eq!((y[0].wins, y[0].votes), (0, 1));
+        assert_eq!((y[1].wins, y[1].votes), (1, 1));
+    }
+
+    #[test]
+    fn rel_vec_redo() {
+        let mut rv = RelVec::create(["a".to_string(), "b".to_string()].to_vec());
+
+        rv.decide(1, 0, Outcome::First, "random");
+        let done = rv.clone();
+        let record = rv.undo().unwrap();
+
+        assert_eq!(rv.redo(&record), Some((1, 0)));
+        assert_eq!(rv[1].elo, done[1].elo);
+        assert_eq!(rv[1].glicko, done[1].glicko);
+        assert_eq!(rv.history.len(), 1);
+    }
+
+    #[test]
+    fn rel_vec_close_period() {
+        let mut rv = RelVec::create(["a".to_string(), "b".to_string(), "c".to_string()].to_vec());
+
+        rv.decide(0, 1, Outcome::First, "");
+        rv.decide(0, 1, Outcome::First, "");
+
+        let a = rv[0].glicko.unwrap();
+        let b = rv[1].glicko.unwrap();
+        assert!(a.rating > 1500.0 && b.rating < 1500.0);
+        assert!(a.deviation < 350.0 && b.deviation < 350.0);
+        assert_eq!(rv[2].glicko, None);
+
+        rv.close_period();
+        rv[2].glicko = Some(a);
+        rv.decide(0, 1, Outcome::First, "");
+        rv.close_period();
+
+        assert!(rv[2].glicko.unwrap().deviation > a.deviation);
+        assert!(rv[0].glicko.unwrap().deviation < a.deviation);
+    }
+
+    #[test]
+    fn rel_vec_seed() {
+        let mut rv = RelVec {
+            inner: [
+                RelEntry::new("a".to_owned(), 3, 4),
+                RelEntry::new("b".to_owned(), 1, 4),
+                RelEntry::new("new".to_owned(), 0, 0),
+            ]
+            .to_vec(),
+            ..RelVec::new()
+        };
+
+        rv[0].elo = Some(1532.0);
+        rv[1].elo = Some(1468.0);
+
+        rv.seed(2, Some(0), Some(1));
+        assert_eq!((rv[2].wins, rv[2].votes), (0, 0));
+        assert_eq!(rv[2].elo, Some(1500.0));
+        assert_eq!(rv[2].bt, None);
+
+        rv.seed(2, None, Some(0));
+        assert_eq!(rv[2].elo, Some(1564.0));
+        assert!(rv[2].glicko.unwrap().rating > rv[0].glicko_rating(&rv.config.glicko).rating);
+
+        rv[0].bt = Some(2.0);
+        rv[1].bt = Some(0.5);
+        rv.seed(2, Some(0), Some(1));
+        assert!((rv[2].strength() - 1.0).abs() < 1e-9);
+
+        rv.seed(2, Some(1), None);
+        assert_eq!(rv[2].elo, Some(1436.0));
+        rv.sort_ranking(Ranking::Elo);
+        assert_eq!(rv[2].name, "new");
+    }
+
+    #[test]
+    fn rel_vec_set_ranks() {
+        let mut rv = RelVec::create(["a".to_string(), "b".to_string(), "c".to_string()].to_vec());
+
+        rv.set_ranks(&["c".to_string(), "a".to_string()]);
+        rv.sort_ranking(Ranking::Rank);
+
+        let ranks: Vec<(&str, Option<u32>)> =
+            rv.iter().map(|e| (e.name.as_str(), e.rank)).collect();
+        assert_eq!(ranks, [("c", Some(1)), ("a", Some(2)), ("b", None)]);
+    }
+
     #[test]
     fn rel_vec_min_votes() {
         let mut rv = RelVec {
@@ -599,29 +1708,49 @@ mod tests {
                     name: "abc".to_string(),
                     wins: 12,
                     votes: 123,
+                    draws: 0,
                     locked: false,
+                    elo: None,
+                    glicko: None,
+                    bt: None,
+                    rank: None,
                 },
                 RelEntry {
                     name: "bcd".to_string(),
                     wins: 125,
                     votes: 123,
+                    draws: 0,
                     locked: false,
+                    elo: None,
+                    glicko: None,
+                    bt: None,
+                    rank: None,
                 },
                 RelEntry {
                     name: "locked".to_string(),
                     wins: 0,
                     votes: 0,
+                    draws: 0,
                     locked: true,
+                    elo: None,
+                    glicko: None,
+                    bt: None,
+                    rank: None,
                 },
                 RelEntry {
                     name: "cde".to_string(),
                     wins: 12,
                     votes: 12632,
+                    draws: 0,
                     locked: false,
+                    elo: None,
+                    glicko: None,
+                    bt: None,
+                    rank: None,
                 },
             ]
             .to_vec(),
-            rng: rand::thread_rng(),
+            ..RelVec::new()
         };
 
         assert_eq!(rv.min_votes(), [0, 1].to_vec());
@@ -636,23 +1765,38 @@ mod tests {
                         name: "abc".to_string(),
                         wins: 0,
                         votes: 0,
+                        draws: 0,
                         locked: false,
+                        elo: None,
+                        glicko: None,
+                        bt: None,
+                        rank: None,
                     },
                     RelEntry {
                         name: "locked".to_string(),
                         wins: 0,
                         votes: 0,
+                        draws: 0,
                         locked: true,
+                        elo: None,
+                        glicko: None,
+                        bt: None,
+                        rank: None,
                     },
                     RelEntry {
                         name: "def".to_string(),
                         wins: 0,
                         votes: 0,
+                        draws: 0,
                         locked: false,
+                        elo: None,
+                        glicko: None,
+                        bt: None,
+                        rank: None,
                     },
                 ]
                 .to_vec(),
-                rng: rand::thread_rng(),
+                ..RelVec::new()
             };
 
             let (a, b) = rv.random_pair().unwrap();
@@ -669,23 +1813,38 @@ mod tests {
                     name: "abc".to_string(),
                     wins: 0,
                     votes: 2,
+                    draws: 0,
                     locked: false,
+                    elo: None,
+                    glicko: None,
+                    bt: None,
+                    rank: None,
                 },
                 RelEntry {
                     name: "locked".to_string(),
                     wins: 0,
                     votes: 0,
+                    draws: 0,
                     locked: true,
+                    elo: None,
+                    glicko: None,
+                    bt: None,
+                    rank: None,
                 },
                 RelEntry {
                     name: "def".to_string(),
                     wins: 0,
                     votes: 1,
+                    draws: 0,
                     locked: false,
+                    elo: None,
+                    glicko: None,
+                    bt: None,
+                    rank: None,
                 },
             ]
             .to_vec(),
-            rng: rand::thread_rng(),
+            ..RelVec::new()
         };
 
         let (a, b) = rv.min_pair().unwrap();
@@ -701,26 +1860,41 @@ mod tests {
                     name: "abc".to_string(),
                     wins: 1,
                     votes: 2,
+                    draws: 0,
                     locked: false,
+                    elo: None,
+                    glicko: None,
+                    bt: None,
+                    rank: None,
                 },
                 RelEntry {
                     name: "locked".to_string(),
                     wins: 1,
                     votes: 1,
+                    draws: 0,
                     locked: false,
+                    elo: None,
+                    glicko: None,
+                    bt: None,
+                    rank: None,
                 },
                 RelEntry {
                     name: "def".to_string(),
                     wins: 1,
                     votes: 2,
+                    draws: 0,
                     locked: false,
+                    elo: None,
+                    glicko: None,
+                    bt: None,
+                    rank: None,
                 },
             ]
             .to_vec(),
-            rng: rand::thread_rng(),
+            ..RelVec::new()
         };
 
-        let (a, b) = rv.equal_pair().unwrap();
+        let (a, b) = rv.equal_pair(Ranking::Percentage).unwrap();
 
         assert!((a, b) == (0, 2) || (a, b) == (2, 0));
     }
@@ -733,26 +1907,41 @@ mod tests {
                     name: "abc".to_string(),
                     wins: 1,
                     votes: 2,
+                    draws: 0,
                     locked: false,
+                    elo: None,
+                    glicko: None,
+                    bt: None,
+                    rank: None,
                 },
                 RelEntry {
                     name: "locked".to_string(),
                     wins: 1,
                     votes: 1,
+                    draws: 0,
                     locked: false,
+                    elo: None,
+                    glicko: None,
+                    bt: None,
+                    rank: None,
                 },
                 RelEntry {
                     name: "def".to_string(),
                     wins: 1,
                     votes: 2,
+                    draws: 0,
                     locked: true,
+                    elo: None,
+                    glicko: None,
+                    bt: None,
+                    rank: None,
                 },
             ]
             .to_vec(),
-            rng: rand::thread_rng(),
+            ..RelVec::new()
         };
 
-        assert_eq!(rv.equal_pair(), None);
+        assert_eq!(rv.equal_pair(Ranking::Percentage), None);
     }
 
     #[test]
@@ -763,26 +1952,41 @@ mod tests {
                     name: "abc".to_string(),
                     wins: 1,
                     votes: 2,
+                    draws: 0,
                     locked: false,
+                    elo: None,
+                    glicko: None,
+                    bt: None,
+                    rank: None,
                 },
                 RelEntry {
                     name: "locked".to_string(),
                     wins: 1,
                     votes: 1,
+                    draws: 0,
                     locked: true,
+                    elo: None,
+                    glicko: None,
+                    bt: None,
+                    rank: None,
                 },
                 RelEntry {
                     name: "def".to_string(),
                     wins: 5,
                     votes: 8,
+                    draws: 0,
                     locked: false,
+                    elo: None,
+                    glicko: None,
+                    bt: None,
+                    rank: None,
                 },
             ]
             .to_vec(),
-            rng: rand::thread_rng(),
+            ..RelVec::new()
         };
 
-        let (a, b) = rv.nearest_pair().unwrap();
+        let (a, b) = rv.nearest_pair(Ranking::Percentage).unwrap();
 
         assert!((a, b) == (0, 2) || (a, b) == (2, 0));
     }
@@ -795,26 +1999,176 @@ mod tests {
                     name: "abc".to_string(),
                     wins: 1,
                     votes: 2,
+                    draws: 0,
                     locked: false,
+                    elo: None,
+                    glicko: None,
+                    bt: None,
+                    rank: None,
                 },
                 RelEntry {
                     name: "locked".to_string(),
                     wins: 1,
                     votes: 1,
+                    draws: 0,
                     locked: false,
+                    elo: None,
+                    glicko: None,
+                    bt: None,
+                    rank: None,
                 },
                 RelEntry {
                     name: "def".to_string(),
                     wins: 5,
                     votes: 8,
+                    draws: 0,
                     locked: true,
+                    elo: None,
+                    glicko: None,
+                    bt: None,
+                    rank: None,
                 },
             ]
             .to_vec(),
-            rng: rand::thread_rng(),
+            ..RelVec::new()
         };
-        let (a, b) = rv.nearest_pair().unwrap();
+        let (a, b) = rv.nearest_pair(Ranking::Percentage).unwrap();
 
         assert!((a, b) == (0, 1) || (a, b) == (1, 0));
     }
+
+    #[test]
+    fn rel_vec_informative_pair() {
+        let mut rv = RelVec::new();
+        rv.add("abc".to_string());
+        rv.add("def".to_string());
+        rv.add("ghi".to_string());
+        rv.add("locked".to_string());
+        rv[3].locked = true;
+        rv.repeat = RepeatPolicy::Allow;
+        for _ in 0..5 {
+            rv.decide(0, 1, Outcome::First, "random");
+        }
+
+        // The only pairs with an unrated entry
+        let (a, b) = rv.informative_pair().unwrap();
+        assert!(a == 2 || b == 2);
+        assert!(a != 3 && b != 3);
+
+        rv.repeat = RepeatPolicy::Never;
+        rv.decide(0, 2, Outcome::First, "random");
+        rv.decide(1, 2, Outcome::First, "random");
+
+        assert_eq!(rv.informative_pair(), None);
+    }
+
+    #[test]
+    fn rel_vec_informative_pair_far() {
+        let mut rv = RelVec::create((0..12).map(|i| i.to_string()).collect());
+        rv.repeat = RepeatPolicy::Never;
+        for a in 0..12 {
+            for b in a + 1..12 {
+                if (a, b) != (0, 11) {
+                    rv.history.push(Record::new(
+                        a.to_string(),
+                        b.to_string(),
+                        Outcome::Draw,
+                        String::new(),
+                    ));
+                }
+            }
+        }
+
+        // Found even if the entries aren't close in rating
+        for _ in 0..5 {
+            let (a, b) = rv.informative_pair().unwrap();
+            assert_eq!(a.min(b), 0);
+            assert_eq!(a.max(b), 11);
+        }
+    }
+
+    #[test]
+    fn rel_vec_swiss_pair() {
+        let mut rv = RelVec::create(
+            ["a", "b", "c", "d", "locked"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        );
+        rv[4].locked = true;
+
+        let mut seen = Vec::new();
+        for _ in 0..2 {
+            let (a, b) = rv.swiss_pair().unwrap();
+            assert!(a != 4 && b != 4);
+            assert!(!seen.contains(&a) && !seen.contains(&b));
+            seen.extend([a, b]);
+            rv.decide(a, b, Outcome::First, "swiss");
+        }
+        assert_eq!(rv.swiss.as_ref().unwrap().round, 1);
+
+        let (a, b) = rv.swiss_pair().unwrap();
+        assert_eq!(rv.swiss.as_ref().unwrap().round, 2);
+        rv.decide(a, b, Outcome::Skip, "swiss");
+        let (c, d) = rv.swiss_pair().unwrap();
+        assert!(c != a && d != a);
+        rv.decide(c, d, Outcome::First, "swiss");
+
+        // The skipped pair is still pending, the undone one again
+        assert_eq!(rv.swiss_pair(), Some((a, b)));
+        rv.undo();
+        rv.undo();
+        rv.undo();
+        assert_eq!(rv.swiss_pair(), Some((seen[2], seen[3])));
+        assert_eq!(rv.swiss.as_ref().unwrap().round, 2);
+    }
+
+    #[test]
+    fn rel_vec_repeat_never() {
+        let mut rv = RelVec::new();
+        rv.add("abc".to_string());
+        rv.add("def".to_string());
+        rv.add("ghi".to_string());
+        rv.repeat = RepeatPolicy::Never;
+        rv.decide(0, 1, Outcome::First, "random");
+        rv.decide(2, 1, Outcome::Draw, "random");
+
+        for _ in 0..5 {
+            let (a, b) = rv.random_pair().unwrap();
+            assert!((a, b) == (0, 2) || (a, b) == (2, 0));
+            let (a, b) = rv.min_pair().unwrap();
+            assert!((a, b) == (0, 2) || (a, b) == (2, 0));
+            let (a, b) = rv.nearest_pair(Ranking::Elo).unwrap();
+            assert!((a, b) == (0, 2) || (a, b) == (2, 0));
+        }
+
+        rv.decide(0, 2, Outcome::Second, "random");
+
+        assert_eq!(rv.random_pair(), None);
+        assert_eq!(rv.min_pair(), None);
+        assert_eq!(rv.nearest_pair(Ranking::Elo), None);
+
+        rv.undo();
+        let (a, b) = rv.random_pair().unwrap();
+        assert!((a, b) == (0, 2) || (a, b) == (2, 0));
+    }
+
+    #[test]
+    fn rel_vec_repeat_exhaust() {
+        let mut rv = RelVec::new();
+        rv.add("abc".to_string());
+        rv.add("def".to_string());
+        rv.add("ghi".to_string());
+        rv.repeat = RepeatPolicy::Exhaust;
+        rv.decide(0, 1, Outcome::First, "random");
+        rv.decide(2, 1, Outcome::First, "random");
+        rv.decide(0, 2, Outcome::First, "random");
+        rv.decide(1, 0, Outcome::First, "random");
+
+        for _ in 0..5 {
+            let (a, b) = rv.random_pair().unwrap();
+            assert!(a != 0 || b != 1);
+            assert!(a != 1 || b != 0);
+        }
+    }
 }
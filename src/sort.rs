@@ -0,0 +1,367 @@
+use std::{
+    cell::RefCell,
+    collections::{HashSet, VecDeque},
+    path::Path,
+    rc::Rc,
+    time::Duration,
+};
+
+use crate::{
+    error::Error,
+    history::{Outcome, Record},
+    journal::{Journal, AUTOSAVE_SECS, AUTOSAVE_VOTES},
+    rating::{Ranking, RatingOverrides},
+    rel_vec::RelVec,
+    storage::Output,
+    vote::{prompt, vote_pairs, ChooseFn},
+};
+
+/// State of an interactive bottom-up merge sort, stored in the list file
+/// between sessions. Entries are identified by name.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MergeSort {
+    /// Sorted runs waiting to be merged, best first
+    runs: VecDeque<Vec<String>>,
+    /// Runs that are being merged
+    left: Vec<String>,
+    right: Vec<String>,
+    /// Merged part of `left` and `right`
+    merged: Vec<String>,
+}
+
+impl MergeSort {
+    pub fn new(names: Vec<String>) -> Self {
+        Self {
+            runs: names.into_iter().map(|n| vec![n]).collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Drops names that aren't part of the list anymore and adds new ones
+    pub fn sync(&mut self, names: &[String]) {
+        let keep: HashSet<&String> = names.iter().collect();
+        let mut known = HashSet::new();
+
+        for v in self
+            .runs
+            .iter_mut()
+            .chain([&mut self.left, &mut self.right, &mut self.merged])
+        {
+            v.retain(|n| keep.contains(n));
+            known.extend(v.iter().cloned());
+        }
+        self.runs.retain(|r| !r.is_empty());
+
+        for n in names.iter().filter(|n| !known.contains(*n)) {
+            self.runs.push_back(vec![n.clone()]);
+        }
+    }
+
+    /// Next pair to compare, `None` once everything is sorted
+    pub fn next_pair(&mut self) -> Option<(String, String)> {
+        loop {
+            if let (Some(a), Some(b)) = (self.left.first(), self.right.first()) {
+                return Some((a.clone(), b.clone()));
+            }
+
+            if !(self.left.is_empty() && self.right.is_empty() && self.merged.is_empty()) {
+                let mut merged = std::mem::take(&mut self.merged);
+                merged.append(&mut self.left);
+                merged.append(&mut self.right);
+                self.runs.push_back(merged);
+            }
+
+            if self.runs.len() < 2 {
+                return None;
+            }
+            self.left = self.runs.pop_front()?;
+            self.right = self.runs.pop_front()?;
+        }
+    }
+
+    /// Takes the answer for the current pair, `first` if the first one is better
+    pub fn answer(&mut self, first: bool) {
+        let side = if first {
+            &mut self.left
+        } else {
+            &mut self.right
+        };
+
+        if !side.is_empty() {
+            let n = side.remove(0);
+            self.merged.push(n);
+        }
+    }
+
+    /// Drops an entry that was removed from the list
+    pub fn remove(&mut self, name: &str) {
+        for v in self
+            .runs
+            .iter_mut()
+            .chain([&mut self.left, &mut self.right, &mut self.merged])
+        {
+            v.retain(|n| n != name);
+        }
+        self.runs.retain(|r| !r.is_empty());
+    }
+
+    /// Upper bound of the comparisons left: every level of merges compares
+    /// each entry at most once
+    pub fn most_comparisons(&self) -> usize {
+        let entries: usize = self
+            .runs
+            .iter()
+            .chain([&self.left, &self.right])
+            .map(|r| r.len())
+            .sum();
+        let runs = self.runs.len() + usize::from(!self.left.is_empty() || !self.right.is_empty());
+        let levels = runs.next_power_of_two().trailing_zeros() as usize;
+
+        entries * levels.max(1)
+    }
+
+    /// Number of runs left, including the ones being merged
+    pub fn runs(&self) -> usize {
+        self.runs.len() + if self.left.is_empty() { 0 } else { 2 }
+    }
+
+    /// The final order, best first, once everything is sorted
+    pub fn sorted(&self) -> Option<&[String]> {
+        if !(self.left.is_empty() && self.right.is_empty() && self.merged.is_empty())
+            || self.runs.len() > 1
+        {
+            return None;
+        }
+
+        Some(self.runs.front().map_or(&[], |r| r.as_slice()))
+    }
+}
+
+/// Strategy name of the decisions of a sort
+const SORT: &str = "sort";
+
+/// Merge sort that follows the decisions of a session. Undone decisions are
+/// taken back by playing the history again from the start.
+struct Follow {
+    start: MergeSort,
+    /// Length of the history `start` belongs to
+    base: usize,
+    state: MergeSort,
+    /// Number of records after `base` that were applied, and the last of them
+    done: usize,
+    last: Option<Record>,
+}
+
+impl Follow {
+    fn new(state: MergeSort, base: usize) -> Self {
+        Self {
+            start: state.clone(),
+            base,
+            state,
+            done: 0,
+            last: None,
+        }
+    }
+
+    /// Applies the records that were added since the last call
+    fn update(&mut self, history: &[Record]) {
+        let records = &history[self.base.min(history.len())..];
+        let valid = self.done <= records.len()
+            && (self.done == 0 || records.get(self.done - 1) == self.last.as_ref());
+        if !valid {
+            self.state = self.start.clone();
+            self.done = 0;
+        }
+
+        for r in &records[self.done..] {
+            match r.outcome {
+                Outcome::First | Outcome::Second | Outcome::Draw
+                    if r.strategy == SORT
+                        && self.state.next_pair() == Some((r.a.clone(), r.b.clone())) =>
+                {
+                    self.state.answer(r.outcome != Outcome::Second)
+                }
+                Outcome::RemoveFirst => self.state.remove(&r.a),
+                Outcome::RemoveSecond => self.state.remove(&r.b),
+                _ => {}
+            }
+        }
+        self.done = records.len();
+        self.last = records.last().cloned();
+    }
+}
+
+/// Sorts all unlocked entries by an interactive merge sort, asked on the same
+/// prompt as `vote`. Progress is saved in the list file when quitting and
+/// picked up by the next call.
+pub(crate) fn sort(
+    input: &str,
+    output: Output,
+    info: bool,
+    tui: bool,
+    restart: bool,
+    overrides: RatingOverrides,
+    voter: Option<String>,
+) -> Result<(), Error> {
+    let mut rv = RelVec::load(input)?;
+    overrides.apply(&mut rv.config);
+    rv.voter = voter;
+
+    let names: Vec<String> = rv
+        .iter()
+        .filter(|e| !e.locked)
+        .map(|e| e.name.clone())
+        .collect();
+    let mut state = match rv.sort.take() {
+        Some(state) if !restart => {
+            println!("Resuming the previous sort");
+            state
+        }
+        _ => MergeSort::new(names.clone()),
+    };
+    state.sync(&names);
+
+    // Votes of a session that stopped before saving continue the sort
+    let follow = Rc::new(RefCell::new(Follow::new(state, rv.history.len())));
+    let mut journal = Journal::new(
+        Path::new(output.path),
+        &rv,
+        AUTOSAVE_VOTES,
+        Some(Duration::from_secs(AUTOSAVE_SECS)),
+    );
+    journal.recover(&mut rv)?;
+    follow.borrow_mut().update(&rv.history);
+
+    let rounds = follow.borrow().state.most_comparisons() as u32;
+    let pairs = Rc::clone(&follow);
+    let mut choose: ChooseFn = Box::new(move |rv: &mut RelVec| {
+        let mut follow = pairs.borrow_mut();
+        follow.update(&rv.history);
+        let (a, b) = follow.state.next_pair()?;
+        rv.position(&a).zip(rv.position(&b))
+    });
+
+    let mut prompt = prompt(tui, input, Ranking::Percentage, info)?;
+    vote_pairs(
+        &mut rv,
+        prompt.as_mut(),
+        &mut choose,
+        &mut journal,
+        rounds,
+        SORT,
+    )?;
+    drop(prompt);
+    drop(choose);
+
+    let mut follow = follow.borrow_mut();
+    follow.update(&rv.history);
+    match follow.state.sorted() {
+        Some(order) => {
+            let order = order.to_vec();
+            rv.set_ranks(&order);
+            println!("Sorted \x1b[34m{}\x1b[0m entries", order.len());
+        }
+        None => {
+            println!(
+                "\x1b[33m{} runs left, sort again to continue\x1b[0m",
+                follow.state.runs()
+            );
+            rv.sort = Some(follow.state.clone());
+        }
+    }
+
+    rv.close_period();
+    rv.save_to(output)?;
+    journal.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Follow, MergeSort, SORT};
+    use crate::history::{Outcome, Record};
+
+    fn names(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// Sorts by answering with the given order
+    fn run(state: &mut MergeSort, truth: &[&str]) -> usize {
+        let mut comparisons = 0;
+
+        while let Some((a, b)) = state.next_pair() {
+            let ia = truth.iter().position(|n| *n == a).unwrap();
+            let ib = truth.iter().position(|n| *n == b).unwrap();
+            state.answer(ia < ib);
+            comparisons += 1;
+        }
+
+        comparisons
+    }
+
+    #[test]
+    fn merge_sort() {
+        let truth = ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"];
+        let mut state = MergeSort::new(names(&["e", "j", "a", "c", "h", "b", "i", "d", "g", "f"]));
+
+        let comparisons = run(&mut state, &truth);
+
+        assert_eq!(state.sorted().unwrap(), names(&truth).as_slice());
+        assert!(comparisons <= 25);
+    }
+
+    #[test]
+    fn merge_sort_sync() {
+        let mut state = MergeSort::new(names(&["c", "a", "b", "x"]));
+
+        state.next_pair();
+        state.answer(false);
+        state.sync(&names(&["a", "b", "c", "d"]));
+        run(&mut state, &["a", "b", "c", "d"]);
+
+        assert_eq!(
+            state.sorted().unwrap(),
+            names(&["a", "b", "c", "d"]).as_slice()
+        );
+    }
+
+    #[test]
+    fn merge_sort_single() {
+        let mut state = MergeSort::new(names(&["a"]));
+
+        assert_eq!(state.next_pair(), None);
+        assert_eq!(state.sorted().unwrap(), names(&["a"]).as_slice());
+    }
+
+    #[test]
+    fn follow_undo() {
+        let mut follow = Follow::new(MergeSort::new(names(&["b", "a", "c"])), 0);
+        let mut history = Vec::new();
+
+        let (a, b) = follow.state.next_pair().unwrap();
+        history.push(Record::new(a, b, Outcome::Second, SORT.to_owned()));
+        follow.update(&history);
+        let second = follow.state.next_pair();
+
+        // Undo and answer the other way
+        history.pop();
+        follow.update(&history);
+        let (a, b) = follow.state.next_pair().unwrap();
+        assert_eq!((a.as_str(), b.as_str()), ("b", "a"));
+        history.push(Record::new(a, b, Outcome::First, SORT.to_owned()));
+        follow.update(&history);
+        assert_ne!(follow.state.next_pair(), second);
+
+        history.push(Record::new(
+            "c".to_owned(),
+            "b".to_owned(),
+            Outcome::RemoveFirst,
+            SORT.to_owned(),
+        ));
+        follow.update(&history);
+        assert_eq!(follow.state.next_pair(), None);
+        assert_eq!(
+            follow.state.sorted().unwrap(),
+            names(&["b", "a"]).as_slice()
+        );
+    }
+}
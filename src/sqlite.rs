@@ -0,0 +1,419 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use rusqlite::{params, Connection, OpenFlags, Row, Transaction};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{
+    backup::{self, sibling},
+    error::Error,
+    history::Record,
+    rating::Glicko,
+    rel_vec::{Meta, RelEntry, RelVec, VERSION},
+    storage::{Backend, Storage, Stored},
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS metadata (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS entries (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    wins INTEGER NOT NULL,
+    draws INTEGER NOT NULL,
+    votes INTEGER NOT NULL,
+    locked INTEGER NOT NULL,
+    elo REAL,
+    glicko_rating REAL,
+    glicko_deviation REAL,
+    glicko_volatility REAL,
+    bt REAL,
+    rank INTEGER
+);
+CREATE TABLE IF NOT EXISTS matches (
+    id INTEGER PRIMARY KEY,
+    a TEXT NOT NULL,
+    b TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    time INTEGER NOT NULL,
+    strategy TEXT NOT NULL,
+    margin INTEGER NOT NULL,
+    voter TEXT,
+    previous TEXT,
+    removed TEXT
+);
+";
+
+/// Lists in an SQLite database with a table each for the metadata, the
+/// entries in order and the recorded matches. Saving a list that was loaded
+/// from the same database only writes the rows that changed.
+pub struct Sqlite;
+
+/// Metadata value stored as JSON
+fn value<T: DeserializeOwned>(
+    meta: &HashMap<String, String>,
+    key: &str,
+) -> Result<Option<T>, Error> {
+    Ok(meta.get(key).map(|v| serde_json::from_str(v)).transpose()?)
+}
+
+/// Optional value stored as JSON in a column
+fn json<T: DeserializeOwned>(column: Option<String>) -> Result<Option<T>, Error> {
+    Ok(column.map(|c| serde_json::from_str(&c)).transpose()?)
+}
+
+fn entry(row: &Row) -> rusqlite::Result<RelEntry> {
+    let glicko = match (row.get(6)?, row.get(7)?, row.get(8)?) {
+        (Some(rating), Some(deviation), Some(volatility)) => Some(Glicko {
+            rating,
+            deviation,
+            volatility,
+        }),
+        _ => None,
+    };
+
+    Ok(RelEntry {
+        name: row.get(0)?,
+        wins: row.get(1)?,
+        draws: row.get(2)?,
+        votes: row.get(3)?,
+        locked: row.get(4)?,
+        elo: row.get(5)?,
+        glicko,
+        bt: row.get(9)?,
+        rank: row.get(10)?,
+    })
+}
+
+/// Whether two entries are stored the same
+fn unchanged(a: &RelEntry, b: &RelEntry) -> bool {
+    (&a.name, a.wins, a.draws, a.votes, a.locked) == (&b.name, b.wins, b.draws, b.votes, b.locked)
+        && (a.elo, a.glicko, a.bt, a.rank) == (b.elo, b.glicko, b.bt, b.rank)
+}
+
+fn entries(conn: &Connection) -> Result<Vec<RelEntry>, Error> {
+    let mut stmt = conn.prepare(
+        "SELECT name, wins, draws, votes, locked, elo, glicko_rating, glicko_deviation,
+            glicko_volatility, bt, rank FROM entries ORDER BY id",
+    )?;
+    let entries = stmt.query_map([], entry)?.collect::<Result<_, _>>()?;
+    Ok(entries)
+}
+
+fn matches(conn: &Connection) -> Result<Vec<Record>, Error> {
+    let mut stmt = conn.prepare(
+        "SELECT a, b, outcome, time, strategy, margin, voter, previous, removed
+            FROM matches ORDER BY id",
+    )?;
+    let mut rows = stmt.query([])?;
+    let mut records = Vec::new();
+    while let Some(row) = rows.next()? {
+        records.push(Record {
+            a: row.get(0)?,
+            b: row.get(1)?,
+            outcome: serde_json::from_value(Value::String(row.get(2)?))?,
+            time: row.get::<_, i64>(3)? as u64,
+            strategy: row.get(4)?,
+            margin: row.get(5)?,
+            voter: row.get(6)?,
+            previous: json(row.get(7)?)?,
+            removed: json(row.get(8)?)?,
+        });
+    }
+    Ok(records)
+}
+
+fn write_meta(tx: &Transaction, rv: &RelVec) -> Result<(), Error> {
+    let mut meta = vec![("version", Some(VERSION.to_string()))];
+    meta.push(("title", rv.meta.title.clone()));
+    meta.push(("created", rv.meta.created.map(|c| c.to_string())));
+    meta.push((
+        "rating",
+        Some(&rv.config)
+            .filter(|c| **c != Default::default())
+            .map(serde_json::to_string)
+            .transpose()?,
+    ));
+    meta.push((
+        "sort",
+        rv.sort.as_ref().map(serde_json::to_string).transpose()?,
+    ));
+    meta.push((
+        "swiss",
+        rv.swiss.as_ref().map(serde_json::to_string).transpose()?,
+    ));
+    meta.push((
+        "bracket",
+        rv.bracket.as_ref().map(serde_json::to_string).transpose()?,
+    ));
+    for (key, value) in meta {
+        match value {
+            Some(value) => tx.execute(
+                "INSERT OR REPLACE INTO metadata (key, value) VALUES (?1, ?2)",
+                params![key, value],
+            )?,
+            None => tx.execute("DELETE FROM metadata WHERE key = ?1", [key])?,
+        };
+    }
+
+    Ok(())
+}
+
+/// Writes the list to a database. With the state of the database as it was
+/// loaded, only entries that differ from it and the records past its common
+/// part are written.
+fn write(rv: &RelVec, file: &Path, stored: Option<&Stored>) -> Result<(), Error> {
+    let (entries, history) = stored.map_or((&[][..], 0), |s| (&s.entries[..], s.history));
+
+    let mut conn = Connection::open(file)?;
+    conn.execute_batch(SCHEMA)?;
+    let tx = conn.transaction()?;
+    write_meta(&tx, rv)?;
+
+    {
+        let mut insert = tx.prepare(
+            "INSERT OR REPLACE INTO entries (id, name, wins, draws, votes, locked, elo,
+                glicko_rating, glicko_deviation, glicko_volatility, bt, rank)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        )?;
+        for (i, e) in rv.iter().enumerate() {
+            if entries.get(i).is_some_and(|s| unchanged(s, e)) {
+                continue;
+            }
+            insert.execute(params![
+                i as i64,
+                e.name,
+                e.wins,
+                e.draws,
+                e.votes,
+                e.locked,
+                e.elo,
+                e.glicko.map(|g| g.rating),
+                e.glicko.map(|g| g.deviation),
+                e.glicko.map(|g| g.volatility),
+                e.bt,
+                e.rank
+            ])?;
+        }
+    }
+    tx.execute("DELETE FROM entries WHERE id >= ?1", [rv.len() as i64])?;
+
+    let history = history.min(rv.history.len());
+    tx.execute("DELETE FROM matches WHERE id >= ?1", [history as i64])?;
+    {
+        let mut insert = tx.prepare(
+            "INSERT INTO matches (id, a, b, outcome, time, strategy, margin, voter, previous,
+                removed) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        )?;
+        for (i, r) in rv.history.iter().enumerate().skip(history) {
+            insert.execute(params![
+                i as i64,
+                r.a,
+                r.b,
+                serde_json::to_value(r.outcome)?.as_str(),
+                r.time as i64,
+                r.strategy,
+                r.margin,
+                r.voter,
+                r.previous.as_ref().map(serde_json::to_string).transpose()?,
+                r.removed.as_ref().map(serde_json::to_string).transpose()?
+            ])?;
+        }
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+impl Storage for Sqlite {
+    fn load(&self, file: &Path) -> Result<RelVec, Error> {
+        let conn = Connection::open_with_flags(file, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+        let mut meta: HashMap<String, String> = HashMap::new();
+        {
+            let mut stmt = conn.prepare("SELECT key, value FROM metadata")?;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                meta.insert(row.get(0)?, row.get(1)?);
+            }
+        }
+
+        let version: u32 = value(&meta, "version")?.unwrap_or(VERSION);
+        if version > VERSION {
+            return Err(Error::Version(version));
+        }
+
+        let mut rv = RelVec::new();
+        rv.meta = Meta {
+            title: meta.get("title").cloned(),
+            created: value(&meta, "created")?,
+        };
+        rv.config = value(&meta, "rating")?.unwrap_or_default();
+        rv.sort = value(&meta, "sort")?;
+        rv.swiss = value(&meta, "swiss")?;
+        rv.bracket = value(&meta, "bracket")?;
+        rv.inner = entries(&conn)?;
+        rv.history = matches(&conn)?;
+        rv.stored = Some(Stored::of(&rv, file));
+
+        Ok(rv)
+    }
+
+    /// Everything is written in one transaction, so the database has either
+    /// the old or the new list. A file that isn't a database yet is replaced
+    /// by a new one. Backups copy the whole database, so they are only kept
+    /// if asked for.
+    fn save(&self, rv: &mut RelVec, file: &Path, keep: Option<usize>) -> Result<(), Error> {
+        let stored = rv.stored.take().filter(|s| s.path == file);
+
+        let result = backup::update(file, keep.unwrap_or(0), || {
+            if Backend::of_file(file)? == Backend::Sqlite {
+                return write(rv, file, stored.as_ref());
+            }
+
+            let tmp = sibling(file, ".tmp");
+            let _ = fs::remove_file(&tmp);
+            let result = write(rv, &tmp, None).and_then(|_| Ok(fs::rename(&tmp, file)?));
+            if result.is_err() {
+                let _ = fs::remove_file(&tmp);
+            }
+            result
+        });
+
+        rv.stored = match result {
+            Ok(()) => Some(Stored::of(rv, file)),
+            Err(_) => stored,
+        };
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::Path};
+
+    use rusqlite::Connection;
+
+    use crate::{
+        backup,
+        history::Outcome,
+        rel_vec::RelVec,
+        storage::{Backend, Output},
+    };
+
+    fn save(rv: &mut RelVec, file: &Path, backend: Option<Backend>) {
+        let output = Output {
+            path: file.to_str().unwrap(),
+            backend,
+            backups: Some(1),
+        };
+        rv.save_to(output).unwrap();
+    }
+
+    #[test]
+    fn sqlite_save_load() {
+        let file = env::temp_dir().join("_relaty_sqlite_save_load.db");
+        let _ = fs::remove_file(&file);
+
+        let mut rv = RelVec::create(vec!["a".to_owned(), "b".to_owned(), "c".to_owned()]);
+        rv.meta.title = Some("Things".to_owned());
+        rv.config.elo.k_factor = 16.0;
+        rv.voter = Some("x".to_owned());
+        rv.decide_by(0, 1, Outcome::First, 2, "random");
+        rv.decide(1, 2, Outcome::Draw, "random");
+        save(&mut rv, &file, Some(Backend::Sqlite));
+
+        let loaded = RelVec::load(&file).unwrap();
+        assert_eq!(loaded.meta, rv.meta);
+        assert_eq!(loaded.config, rv.config);
+        assert_eq!(
+            serde_json::to_string(&loaded.inner).unwrap(),
+            serde_json::to_string(&rv.inner).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_string(&loaded.history).unwrap(),
+            serde_json::to_string(&rv.history).unwrap()
+        );
+
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn sqlite_update() {
+        let file = env::temp_dir().join("_relaty_sqlite_update.db");
+        let _ = fs::remove_file(&file);
+        let _ = fs::remove_file(backup::path(&file, 1));
+
+        let mut rv = RelVec::create(vec!["a".to_owned(), "b".to_owned(), "c".to_owned()]);
+        rv.decide(0, 1, Outcome::First, "random");
+        rv.decide(1, 2, Outcome::First, "random");
+        save(&mut rv, &file, Some(Backend::Sqlite));
+
+        let mut rv = RelVec::load(&file).unwrap();
+        rv.undo();
+        rv.decide(0, 2, Outcome::RemoveSecond, "random");
+        save(&mut rv, &file, None);
+
+        let conn = Connection::open(&file).unwrap();
+        let count = |table: &str| -> i64 {
+            conn.query_row(&format!("SELECT count(*) FROM {}", table), [], |r| r.get(0))
+                .unwrap()
+        };
+        assert_eq!(count("entries"), 2);
+        assert_eq!(count("matches"), 2);
+        let outcome: String = conn
+            .query_row("SELECT outcome FROM matches WHERE id = 1", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(outcome, "y");
+
+        let loaded = RelVec::load(&file).unwrap();
+        assert_eq!(
+            loaded.iter().map(|e| e.name.as_str()).collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+        assert_eq!(loaded[0].wins, 1);
+
+        // The previous database is kept as backup
+        let backup = RelVec::load(backup::path(&file, 1)).unwrap();
+        assert_eq!(backup.len(), 3);
+        assert_eq!(backup.history.len(), 2);
+
+        // Unless asked for, databases have no backups
+        let mut rv = loaded;
+        rv.decide(0, 1, Outcome::Draw, "random");
+        let output = Output {
+            path: file.to_str().unwrap(),
+            backend: None,
+            backups: None,
+        };
+        rv.save_to(output).unwrap();
+        assert!(!backup::path(&file, 2).exists());
+        assert_eq!(RelVec::load(backup::path(&file, 1)).unwrap().len(), 3);
+
+        fs::remove_file(backup::path(&file, 1)).unwrap();
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn sqlite_replace_json() {
+        let file = env::temp_dir().join("_relaty_sqlite_replace_json.db");
+        let _ = fs::remove_file(&file);
+
+        // A JSON list named like a database stays JSON
+        let mut rv = RelVec::create(vec!["a".to_owned(), "b".to_owned()]);
+        save(&mut rv, &file, None);
+        assert_eq!(Backend::of_file(&file).unwrap(), Backend::Json);
+        assert_eq!(RelVec::load(&file).unwrap().len(), 2);
+
+        save(&mut rv, &file, Some(Backend::Sqlite));
+        assert_eq!(Backend::of_file(&file).unwrap(), Backend::Sqlite);
+        assert_eq!(RelVec::load(&file).unwrap().len(), 2);
+
+        for n in 1..=2 {
+            let _ = fs::remove_file(backup::path(&file, n));
+        }
+        fs::remove_file(file).unwrap();
+    }
+}
@@ -0,0 +1,281 @@
+use std::{
+    convert::TryFrom,
+    fmt,
+    fs::File,
+    io::{self, BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    backup,
+    bracket::Bracket,
+    error::Error,
+    history::Record,
+    rating::RatingConfig,
+    rel_vec::{Meta, RelEntry, RelVec, VERSION},
+    sort::MergeSort,
+    swiss::Swiss,
+};
+
+/// First bytes of every SQLite database
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// Reads and writes lists in one kind of file
+pub(crate) trait Storage {
+    fn load(&self, file: &Path) -> Result<RelVec, Error>;
+
+    /// Saves the list and keeps up to `keep` backups of the file, `None` for
+    /// the default of the kind of file
+    fn save(&self, rv: &mut RelVec, file: &Path, keep: Option<usize>) -> Result<(), Error>;
+}
+
+/// A list as it is in a file, so that saving it there again only has to
+/// write what changed
+#[derive(Clone, Debug)]
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+pub struct Stored {
+    pub path: PathBuf,
+    pub entries: Vec<RelEntry>,
+    /// Number of records at the start of the history that are in the file
+    pub history: usize,
+}
+
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+impl Stored {
+    pub fn of(rv: &RelVec, file: &Path) -> Self {
+        Self {
+            path: file.to_owned(),
+            entries: rv.inner.clone(),
+            history: rv.history.len(),
+        }
+    }
+}
+
+/// File a command saves its list to
+#[derive(Clone, Copy, Debug)]
+pub struct Output<'a> {
+    pub path: &'a str,
+    /// Kind of file to write, `None` keeps the kind of an existing file and
+    /// writes JSON otherwise
+    pub backend: Option<Backend>,
+    /// Number of backups to keep, `None` for the default of the kind of file
+    pub backups: Option<usize>,
+}
+
+impl<'a> From<&'a str> for Output<'a> {
+    fn from(path: &'a str) -> Self {
+        Self {
+            path,
+            backend: None,
+            backups: backup::count(),
+        }
+    }
+}
+
+/// Kind of list file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    Json,
+    Sqlite,
+}
+
+impl Backend {
+    pub fn backends() -> [&'static str; 2] {
+        ["json", "sqlite"]
+    }
+
+    /// Kind of a file by its first bytes. Files that aren't SQLite
+    /// databases, including missing ones, are JSON.
+    pub fn of_file(path: &Path) -> Result<Self, Error> {
+        let mut header = Vec::new();
+        match File::open(path) {
+            Ok(file) => file
+                .take(SQLITE_HEADER.len() as u64)
+                .read_to_end(&mut header)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e.into()),
+        };
+
+        if header == SQLITE_HEADER {
+            Ok(Backend::Sqlite)
+        } else {
+            Ok(Backend::Json)
+        }
+    }
+
+    pub fn storage(&self) -> Result<Box<dyn Storage>, Error> {
+        match self {
+            Backend::Json => Ok(Box::new(Json)),
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite => Ok(Box::new(crate::sqlite::Sqlite)),
+            #[cfg(not(feature = "sqlite"))]
+            Backend::Sqlite => Err(Error::NoSqlite),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::Json => write!(f, "json"),
+            Backend::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+impl TryFrom<&str> for Backend {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "json" => Ok(Backend::Json),
+            "sqlite" => Ok(Backend::Sqlite),
+            _ => Err(Error::ArgError),
+        }
+    }
+}
+
+/// Meta object of a file, with the rating settings the list was saved with
+#[derive(Default, Deserialize)]
+struct MetaFile {
+    #[serde(flatten)]
+    meta: Meta,
+    #[serde(default)]
+    rating: Option<RatingConfig>,
+}
+
+/// Layout of a list file
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ListFile {
+    Versioned {
+        version: u32,
+        #[serde(default)]
+        meta: MetaFile,
+        entries: Vec<RelEntry>,
+        #[serde(default)]
+        history: Vec<Record>,
+        #[serde(default)]
+        sort: Option<MergeSort>,
+        #[serde(default)]
+        swiss: Option<Swiss>,
+        #[serde(default)]
+        bracket: Option<Bracket>,
+    },
+    /// Object without version and meta
+    List {
+        inner: Vec<RelEntry>,
+        #[serde(default)]
+        history: Vec<Record>,
+        #[serde(default)]
+        sort: Option<MergeSort>,
+        #[serde(default)]
+        swiss: Option<Swiss>,
+    },
+    /// Plain array of entries
+    Legacy(Vec<RelEntry>),
+}
+
+#[derive(Serialize)]
+struct MetaRef<'a> {
+    #[serde(flatten)]
+    meta: &'a Meta,
+    /// Only settings that differ from the defaults are written
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rating: Option<&'a RatingConfig>,
+}
+
+#[derive(Serialize)]
+struct ListFileRef<'a> {
+    version: u32,
+    meta: MetaRef<'a>,
+    entries: &'a [RelEntry],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    history: &'a [Record],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sort: &'a Option<MergeSort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    swiss: &'a Option<Swiss>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bracket: &'a Option<Bracket>,
+}
+
+/// Lists in a JSON file. Older layouts are read as well.
+pub struct Json;
+
+impl Storage for Json {
+    fn load(&self, file: &Path) -> Result<RelVec, Error> {
+        let reader = BufReader::new(File::open(file)?);
+        let (meta, inner, history, sort, swiss, bracket) = match serde_json::from_reader(reader)? {
+            ListFile::Versioned { version, .. } if version > VERSION => {
+                return Err(Error::Version(version))
+            }
+            ListFile::Versioned {
+                meta,
+                entries,
+                history,
+                sort,
+                swiss,
+                bracket,
+                ..
+            } => (meta, entries, history, sort, swiss, bracket),
+            ListFile::List {
+                inner,
+                history,
+                sort,
+                swiss,
+            } => (MetaFile::default(), inner, history, sort, swiss, None),
+            ListFile::Legacy(inner) => (MetaFile::default(), inner, Vec::new(), None, None, None),
+        };
+
+        let mut rv = RelVec::new();
+        rv.inner = inner;
+        rv.history = history;
+        rv.sort = sort;
+        rv.swiss = swiss;
+        rv.bracket = bracket;
+        rv.meta = meta.meta;
+        rv.config = meta.rating.unwrap_or_default();
+        Ok(rv)
+    }
+
+    /// The file is replaced at once and its previous content kept as backup
+    fn save(&self, rv: &mut RelVec, file: &Path, keep: Option<usize>) -> Result<(), Error> {
+        let rating = Some(&rv.config).filter(|c| **c != RatingConfig::default());
+        let list = ListFileRef {
+            version: VERSION,
+            meta: MetaRef {
+                meta: &rv.meta,
+                rating,
+            },
+            entries: &rv.inner,
+            history: &rv.history,
+            sort: &rv.sort,
+            swiss: &rv.swiss,
+            bracket: &rv.bracket,
+        };
+
+        backup::write(file, keep.unwrap_or(backup::DEFAULT_BACKUPS), |writer| {
+            Ok(serde_json::to_writer(writer, &list)?)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::Backend;
+
+    #[test]
+    fn storage_of_file() {
+        let file = env::temp_dir().join("_relaty_storage_of_file.db");
+
+        fs::write(&file, b"SQLite format 3\0\x10\0").unwrap();
+        assert_eq!(Backend::of_file(&file).unwrap(), Backend::Sqlite);
+        fs::write(&file, b"[]").unwrap();
+        assert_eq!(Backend::of_file(&file).unwrap(), Backend::Json);
+        fs::remove_file(&file).unwrap();
+        assert_eq!(Backend::of_file(&file).unwrap(), Backend::Json);
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+
+use crate::history::{PairWeights, RepeatPolicy};
+
+/// Strategy name of the decisions that count for the tournament
+pub const SWISS: &str = "swiss";
+
+/// State of a Swiss-system tournament, stored in the list file between
+/// sessions
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Swiss {
+    /// Current round, 0 before the first one
+    pub round: u32,
+    /// Pairs of all rounds, oldest first
+    pairings: Vec<(String, String)>,
+    /// Decided games with the score of the first entry
+    games: Vec<(String, String, f64)>,
+    /// Entries that sat out a round, each counts as a win
+    byes: Vec<String>,
+}
+
+/// Score of an entry in the tournament
+#[derive(Clone, Debug, PartialEq)]
+pub struct Standing {
+    pub name: String,
+    pub points: f64,
+    /// Sum of the points of all opponents
+    pub buchholz: f64,
+    pub games: u32,
+}
+
+fn same(a: &str, b: &str, pair: (&str, &str)) -> bool {
+    (a, b) == pair || (b, a) == pair
+}
+
+impl Swiss {
+    /// Pairs that were paired more often than they played, oldest first
+    pub fn pending(&self) -> Vec<&(String, String)> {
+        let mut played: HashMap<(&str, &str), usize> = HashMap::new();
+        for (a, b, _) in &self.games {
+            *played.entry((a, b)).or_insert(0) += 1;
+        }
+
+        self.pairings
+            .iter()
+            .filter(|(a, b)| {
+                for key in [(a.as_str(), b.as_str()), (b.as_str(), a.as_str())] {
+                    if let Some(n) = played.get_mut(&key).filter(|n| **n > 0) {
+                        *n -= 1;
+                        return false;
+                    }
+                }
+                true
+            })
+            .collect()
+    }
+
+    /// Counts a decision between two entries if they still have to play
+    pub fn play(&mut self, a: &str, b: &str, score: f64) {
+        if self.pending().iter().any(|(x, y)| same(x, y, (a, b))) {
+            self.games.push((a.to_owned(), b.to_owned(), score));
+        }
+    }
+
+    /// Takes back the last game between two entries
+    pub fn unplay(&mut self, a: &str, b: &str) {
+        if let Some(i) = self.games.iter().rposition(|(x, y, _)| same(x, y, (a, b))) {
+            self.games.remove(i);
+        }
+    }
+
+    /// Points and Buchholz scores of the given entries, best first
+    pub fn standings(&self, names: &[String]) -> Vec<Standing> {
+        let mut points: HashMap<&str, f64> = names.iter().map(|n| (n.as_str(), 0.0)).collect();
+        let mut opponents: HashMap<&str, Vec<&str>> = HashMap::new();
+
+        for (a, b, score) in &self.games {
+            *points.entry(a).or_insert(0.0) += score;
+            *points.entry(b).or_insert(0.0) += 1.0 - score;
+            opponents.entry(a).or_default().push(b);
+            opponents.entry(b).or_default().push(a);
+        }
+        for n in &self.byes {
+            *points.entry(n).or_insert(0.0) += 1.0;
+        }
+
+        let mut standings: Vec<Standing> = names
+            .iter()
+            .map(|n| {
+                let opponents = opponents.get(n.as_str()).map_or(&[][..], |o| o.as_slice());
+                Standing {
+                    name: n.clone(),
+                    points: points[n.as_str()],
+                    buchholz: opponents.iter().fold(0.0, |sum, o| sum + points[o]),
+                    games: opponents.len() as u32,
+                }
+            })
+            .collect();
+
+        standings.sort_by(|a, b| {
+            b.points
+                .total_cmp(&a.points)
+                .then(b.buchholz.total_cmp(&a.buchholz))
+        });
+        standings
+    }
+
+    /// Starts the next round. Entries with similar points are paired,
+    /// preferring opponents `repeat` weighs higher; entries it allows no
+    /// opponent for sit out the round. With an odd number the lowest one
+    /// without a bye sits out and gets a point. Ties are kept in the order of
+    /// `names`. Returns `false` and keeps the state if no pair is left.
+    pub fn next_round(&mut self, names: &[String], repeat: RepeatPolicy) -> bool {
+        let refs: Vec<&str> = names.iter().map(|n| n.as_str()).collect();
+        let games = self
+            .games
+            .iter()
+            .map(|(a, b, _)| Some((a.as_str(), b.as_str())));
+        let weights = PairWeights::of_pairs(games, &refs, repeat);
+
+        let mut order: Vec<String> = self.standings(names).into_iter().map(|s| s.name).collect();
+
+        let mut bye = None;
+        if order.len() % 2 == 1 {
+            let i = order
+                .iter()
+                .rposition(|n| !self.byes.contains(n))
+                .unwrap_or(order.len() - 1);
+            bye = Some(order.remove(i));
+        }
+
+        let mut pairings = Vec::new();
+        while !order.is_empty() {
+            let a = order.remove(0);
+            let mut best: Option<(usize, f64)> = None;
+            for (i, b) in order.iter().enumerate() {
+                let w = weights.weight(&a, b);
+                if w > 0.0 && best.is_none_or(|(_, max)| w > max) {
+                    best = Some((i, w));
+                }
+            }
+            if let Some((i, _)) = best {
+                let b = order.remove(i);
+                pairings.push((a, b));
+            }
+        }
+
+        if pairings.is_empty() {
+            return false;
+        }
+
+        self.round += 1;
+        self.pairings.extend(pairings);
+        self.byes.extend(bye);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Swiss;
+    use crate::history::RepeatPolicy;
+
+    fn names(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn play(swiss: &mut Swiss) {
+        let pending: Vec<(String, String)> = swiss.pending().into_iter().cloned().collect();
+        for (a, b) in pending {
+            // The name first in the alphabet wins
+            let score = if a < b { 1.0 } else { 0.0 };
+            swiss.play(&a, &b, score);
+        }
+    }
+
+    #[test]
+    fn swiss_rounds() {
+        let names = names(&["a", "b", "c", "d"]);
+        let mut swiss = Swiss::default();
+
+        assert!(swiss.next_round(&names, RepeatPolicy::Never));
+        assert_eq!(swiss.round, 1);
+        assert_eq!(swiss.pending().len(), 2);
+        play(&mut swiss);
+        assert!(swiss.pending().is_empty());
+
+        // Winners meet winners
+        assert!(swiss.next_round(&names, RepeatPolicy::Never));
+        let pending = swiss.pending();
+        assert!(pending.contains(&&("a".to_string(), "c".to_string())));
+        assert!(pending.contains(&&("b".to_string(), "d".to_string())));
+        play(&mut swiss);
+
+        assert!(swiss.next_round(&names, RepeatPolicy::Never));
+        play(&mut swiss);
+
+        let standings = swiss.standings(&names);
+        assert_eq!(standings[0].name, "a");
+        assert_eq!(standings[0].points, 3.0);
+        assert_eq!(standings[3].points, 0.0);
+        assert!(standings.iter().all(|s| s.games == 3));
+
+        // Everybody met everybody
+        assert!(!swiss.next_round(&names, RepeatPolicy::Never));
+        assert_eq!(swiss.round, 3);
+        assert!(swiss.next_round(&names, RepeatPolicy::Allow));
+        assert_eq!(swiss.pending().len(), 2);
+    }
+
+    #[test]
+    fn swiss_bye() {
+        let names = names(&["a", "b", "c"]);
+        let mut swiss = Swiss::default();
+
+        swiss.next_round(&names, RepeatPolicy::Never);
+        assert_eq!(swiss.pending().len(), 1);
+        play(&mut swiss);
+        swiss.next_round(&names, RepeatPolicy::Never);
+        play(&mut swiss);
+
+        assert_eq!(swiss.byes.len(), 2);
+        assert_ne!(swiss.byes[0], swiss.byes[1]);
+        let points: f64 = swiss.standings(&names).iter().map(|s| s.points).sum();
+        assert_eq!(points, 4.0);
+    }
+
+    #[test]
+    fn swiss_unplay() {
+        let names = names(&["a", "b"]);
+        let mut swiss = Swiss::default();
+
+        swiss.next_round(&names, RepeatPolicy::Allow);
+        swiss.play("b", "a", 1.0);
+        assert!(swiss.pending().is_empty());
+
+        // Only pending pairs count
+        swiss.play("a", "b", 1.0);
+        assert_eq!(swiss.standings(&names)[0].points, 1.0);
+
+        swiss.unplay("a", "b");
+        assert_eq!(swiss.pending().len(), 1);
+        assert_eq!(swiss.standings(&names)[0].points, 0.0);
+    }
+
+    #[test]
+    fn swiss_buchholz() {
+        let names = names(&["a", "b", "c", "d"]);
+        let swiss = Swiss {
+            games: vec![
+                ("a".into(), "b".into(), 1.0),
+                ("c".into(), "d".into(), 1.0),
+                ("b".into(), "d".into(), 1.0),
+                ("c".into(), "a".into(), 0.5),
+            ],
+            ..Swiss::default()
+        };
+
+        let standings = swiss.standings(&names);
+
+        // a and c both have 1.5 points, a's opponents scored more
+        assert_eq!(standings[0].name, "a");
+        assert_eq!(standings[0].buchholz, 2.5);
+        assert_eq!(standings[1].name, "c");
+        assert_eq!(standings[1].buchholz, 1.5);
+    }
+}
@@ -0,0 +1,361 @@
+use std::{
+    env,
+    io::{self, Stdout, Write},
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{self, ClearType},
+    tty::IsTty,
+};
+
+use crate::{
+    error::Error,
+    rating::Ranking,
+    rel_vec::RelVec,
+    vote::{entry, Prompt},
+};
+
+/// Number of entries in the leaderboard
+const LEADERBOARD: usize = 5;
+/// Width of the progress bar
+const PROGRESS_WIDTH: usize = 30;
+
+/// Full-screen prompt reading single keys
+pub(crate) struct Tui {
+    out: Stdout,
+    title: String,
+    ranking: Ranking,
+    info: bool,
+    /// Message about the last command
+    status: Option<String>,
+    help: bool,
+}
+
+/// Command of a key, arrows and some other keys are shortcuts
+fn key_command(key: KeyEvent) -> Option<char> {
+    match key.code {
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some('q'),
+        KeyCode::Char(' ') => Some('o'),
+        KeyCode::Char(c) => Some(c),
+        KeyCode::Left => Some('1'),
+        KeyCode::Right => Some('2'),
+        KeyCode::Up | KeyCode::Down => Some('='),
+        KeyCode::Backspace => Some('u'),
+        KeyCode::Esc => Some('q'),
+        _ => None,
+    }
+}
+
+/// Shortcut shown next to a command
+fn shortcut(c: char) -> &'static str {
+    match c {
+        '1' => "←",
+        '2' => "→",
+        '!' => "shift ← →",
+        '=' => "↑ ↓",
+        'o' => "space",
+        'u' => "backspace",
+        'q' => "esc",
+        _ => "",
+    }
+}
+
+impl Tui {
+    /// Whether the terminal supports the full-screen prompt
+    pub fn available() -> bool {
+        io::stdin().is_tty()
+            && io::stdout().is_tty()
+            && env::var("TERM").map_or(true, |t| t != "dumb")
+    }
+
+    pub fn new(title: &str, ranking: Ranking, info: bool) -> Result<Self, Error> {
+        let mut out = io::stdout();
+
+        terminal::enable_raw_mode()?;
+        execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+        Ok(Self {
+            out,
+            title: title.to_owned(),
+            ranking,
+            info,
+            status: None,
+            help: false,
+        })
+    }
+
+    /// Lines of the screen for a pair or a group of entries. `input` is the
+    /// ranking typed so far for a group.
+    fn screen(
+        &self,
+        rv: &RelVec,
+        items: &[usize],
+        commands: &[(char, String)],
+        (done, all): (u32, u32),
+        input: &str,
+    ) -> Vec<String> {
+        let filled = (done as usize * PROGRESS_WIDTH)
+            .checked_div(all as usize)
+            .unwrap_or(0)
+            .min(PROGRESS_WIDTH);
+        let mut lines = vec![
+            format!("\x1b[1mrelaty\x1b[0m - {}", self.title),
+            format!(
+                "\x1b[34m{}\x1b[90m{}\x1b[0m {}/{}",
+                "█".repeat(filled),
+                "░".repeat(PROGRESS_WIDTH - filled),
+                done,
+                all
+            ),
+            String::new(),
+        ];
+        for (n, &i) in items.iter().enumerate() {
+            if n == 1 && items.len() == 2 {
+                lines.push("        \x1b[31;4mvs.\x1b[0m".to_owned());
+            }
+            lines.push(format!(
+                "  \x1b[34m({})\x1b[0m {}",
+                n + 1,
+                entry(rv, i, self.info)
+            ));
+        }
+        lines.push(String::new());
+
+        if self.help {
+            lines.push("  Keys".to_owned());
+            if items.len() == 2 {
+                for (n, &i) in items.iter().enumerate() {
+                    let key = if n == 0 { '1' } else { '2' };
+                    lines.push(format!(
+                        "  \x1b[35m{}\x1b[0m, {:<9} {} is better",
+                        key,
+                        shortcut(key),
+                        rv[i].name
+                    ));
+                }
+            } else {
+                lines.push(format!(
+                    "  \x1b[35m1\x1b[0m-\x1b[35m{}\x1b[0m {:<9} Rank the entries, the first one is the best",
+                    items.len(),
+                    ""
+                ));
+                lines.push(format!(
+                    "  \x1b[35menter\x1b[0m  {:<8} Only the entries typed so far are better",
+                    ""
+                ));
+            }
+            for (key, description) in commands {
+                lines.push(format!(
+                    "  \x1b[35m{}\x1b[0m, {:<9} {}",
+                    key,
+                    shortcut(*key),
+                    description
+                ));
+            }
+            lines.push(format!("  \x1b[35m?\x1b[0m  {:<9} Toggle this help", ""));
+            lines.push(String::new());
+            lines.push("  \x1b[90mPress any key to close\x1b[0m".to_owned());
+            return lines;
+        }
+
+        if items.len() == 2 {
+            lines.push(
+                "  \x1b[35m←\x1b[0m first  \x1b[35m→\x1b[0m second  \x1b[35m↓\x1b[0m equal  \x1b[35m!\x1b[0m clearly  \x1b[35mspace\x1b[0m skip  \x1b[35mq\x1b[0m quit  \x1b[35m?\x1b[0m help"
+                    .to_owned(),
+            );
+            if input == "!" {
+                lines.push("  \x1b[1mClearly better:\x1b[0m _".to_owned());
+            }
+        } else {
+            lines.push(format!(
+                "  \x1b[35m1\x1b[0m-\x1b[35m{}\x1b[0m rank  \x1b[35menter\x1b[0m done  \x1b[35mspace\x1b[0m skip  \x1b[35mq\x1b[0m quit  \x1b[35m?\x1b[0m help",
+                items.len()
+            ));
+            lines.push(format!("  Ranking: \x1b[1m{}\x1b[0m_", input));
+        }
+        lines.push(String::new());
+
+        let mut top: Vec<_> = rv.iter().filter(|e| !e.locked).collect();
+        top.sort_by(|x, y| self.ranking.compare(x, y, &rv.config).reverse());
+        lines.push(format!("  Top entries ({})", self.ranking));
+        for (i, e) in top.iter().take(LEADERBOARD).enumerate() {
+            let value = self
+                .ranking
+                .describe(e, &rv.config)
+                .unwrap_or_else(|| format!("{:.1}%", e.percentage()));
+            lines.push(format!(
+                "  \x1b[33m{:>2}.\x1b[0m {} \x1b[90m- {}\x1b[0m",
+                i + 1,
+                e.name,
+                value
+            ));
+        }
+
+        if let Some(status) = &self.status {
+            lines.push(String::new());
+            lines.push(format!("  {}", status));
+        }
+
+        lines
+    }
+
+    fn draw(&mut self, lines: &[String]) -> Result<(), Error> {
+        queue!(self.out, terminal::Clear(ClearType::All))?;
+        for (row, line) in lines.iter().enumerate() {
+            queue!(self.out, cursor::MoveTo(0, row as u16), Print(line))?;
+        }
+        self.out.flush()?;
+
+        Ok(())
+    }
+}
+
+impl Prompt for Tui {
+    fn ask(
+        &mut self,
+        rv: &RelVec,
+        a: usize,
+        b: usize,
+        commands: &[(char, String)],
+        progress: (u32, u32),
+    ) -> Result<Option<String>, Error> {
+        // `!` was typed, the next entry is clearly better
+        let mut clear = false;
+
+        loop {
+            let input = if clear { "!" } else { "" };
+            let lines = self.screen(rv, &[a, b], commands, progress, input);
+            self.draw(&lines)?;
+
+            let key = match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => key,
+                _ => continue,
+            };
+
+            if self.help {
+                self.help = false;
+                continue;
+            }
+
+            let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+            match key_command(key) {
+                Some('?') => self.help = true,
+                Some('!') => clear = !clear,
+                Some(c) if c == '1' || c == '2' => {
+                    self.status = None;
+                    let clear =
+                        clear || (shift && matches!(key.code, KeyCode::Left | KeyCode::Right));
+                    return Ok(Some(if clear {
+                        format!("{}!", c)
+                    } else {
+                        c.to_string()
+                    }));
+                }
+                Some(c) if commands.iter().any(|(k, _)| *k == c) => {
+                    self.status = None;
+                    return Ok(Some(c.to_string()));
+                }
+                Some(c) => self.status = Some(format!("\x1b[31munknown key {}\x1b[0m", c)),
+                None => {}
+            }
+        }
+    }
+
+    fn ask_group(
+        &mut self,
+        rv: &RelVec,
+        items: &[usize],
+        commands: &[(char, String)],
+        progress: (u32, u32),
+    ) -> Result<Option<String>, Error> {
+        let mut input = String::new();
+
+        loop {
+            let lines = self.screen(rv, items, commands, progress, &input);
+            self.draw(&lines)?;
+
+            let key = match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => key,
+                _ => continue,
+            };
+
+            if self.help {
+                self.help = false;
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Enter if !input.is_empty() => {
+                    self.status = None;
+                    return Ok(Some(input));
+                }
+                KeyCode::Backspace if !input.is_empty() => {
+                    input.pop();
+                    continue;
+                }
+                _ => {}
+            }
+
+            let number = |c: char| {
+                c.to_digit(10)
+                    .is_some_and(|d| d >= 1 && d as usize <= items.len())
+            };
+            match key_command(key) {
+                Some('?') => self.help = true,
+                Some(c) if number(c) => {
+                    if !input.contains(c) {
+                        input.push(c);
+                    }
+                    // The last entry is implied
+                    if input.len() + 1 >= items.len() {
+                        self.status = None;
+                        return Ok(Some(input));
+                    }
+                }
+                Some(c) if input.is_empty() && commands.iter().any(|(k, _)| *k == c) => {
+                    self.status = None;
+                    return Ok(Some(c.to_string()));
+                }
+                Some(c) => self.status = Some(format!("\x1b[31munknown key {}\x1b[0m", c)),
+                None => {}
+            }
+        }
+    }
+
+    fn message(&mut self, message: &str) {
+        self.status = Some(message.to_owned());
+    }
+}
+
+impl Drop for Tui {
+    fn drop(&mut self) {
+        let _ = execute!(self.out, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::key_command;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    #[test]
+    fn tui_key_command() {
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        assert_eq!(key_command(key(KeyCode::Left)), Some('1'));
+        assert_eq!(key_command(key(KeyCode::Right)), Some('2'));
+        assert_eq!(key_command(key(KeyCode::Down)), Some('='));
+        assert_eq!(key_command(key(KeyCode::Char(' '))), Some('o'));
+        assert_eq!(key_command(key(KeyCode::Char('x'))), Some('x'));
+        assert_eq!(
+            key_command(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Some('q')
+        );
+        assert_eq!(key_command(key(KeyCode::Home)), None);
+    }
+}
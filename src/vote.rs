@@ -1,9 +1,24 @@
 use std::{
     convert::TryFrom,
+    fmt,
     io::{self, Write},
+    path::Path,
+    time::Duration,
 };
 
-use crate::{error::Error, rel_vec::RelVec};
+use crate::{
+    error::Error,
+    group::vote_group,
+    history::{Outcome, Record, RepeatPolicy, CLEAR},
+    journal::Journal,
+    rating::{Ranking, RatingOverrides},
+    rel_vec::RelVec,
+    storage::Output,
+    tui::Tui,
+};
+
+/// Picks the next pair to vote on
+pub type ChooseFn = Box<dyn FnMut(&mut RelVec) -> Option<(usize, usize)>>;
 
 pub enum VoteStrategy {
     Random,
@@ -11,32 +26,46 @@ pub enum VoteStrategy {
     Equal,
     MinEqual,
     Nearest,
+    Information,
+    Swiss,
 }
 
 impl VoteStrategy {
-    pub fn strategies() -> [&'static str; 5] {
-        ["random", "onemin", "equal", "minequal", "nearest"]
+    pub fn strategies() -> [&'static str; 7] {
+        [
+            "random",
+            "onemin",
+            "equal",
+            "minequal",
+            "nearest",
+            "information",
+            "swiss",
+        ]
     }
 
-    pub fn choose_function(&self) -> Box<dyn FnMut(&mut RelVec) -> Option<(usize, usize)>> {
+    pub fn choose_function(&self, ranking: Ranking) -> ChooseFn {
         match self {
             VoteStrategy::Random => Box::new(RelVec::random_pair),
             VoteStrategy::OneMin => Box::new(RelVec::min_pair),
-            VoteStrategy::Equal => Box::new(RelVec::equal_pair),
-            VoteStrategy::MinEqual => Box::new(RelVec::min_equal_pair),
-            VoteStrategy::Nearest => Box::new(RelVec::nearest_pair),
+            VoteStrategy::Equal => Box::new(move |rv: &mut RelVec| rv.equal_pair(ranking)),
+            VoteStrategy::MinEqual => Box::new(move |rv: &mut RelVec| rv.min_equal_pair(ranking)),
+            VoteStrategy::Nearest => Box::new(move |rv: &mut RelVec| rv.nearest_pair(ranking)),
+            VoteStrategy::Information => Box::new(RelVec::informative_pair),
+            VoteStrategy::Swiss => Box::new(RelVec::swiss_pair),
         }
     }
 }
 
-impl ToString for VoteStrategy {
-    fn to_string(&self) -> String {
+impl fmt::Display for VoteStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            VoteStrategy::Random => "random".to_owned(),
-            VoteStrategy::OneMin => "onemin".to_owned(),
-            VoteStrategy::Equal => "equal".to_owned(),
-            VoteStrategy::MinEqual => "minequal".to_owned(),
-            VoteStrategy::Nearest => "nearest".to_owned(),
+            VoteStrategy::Random => write!(f, "random"),
+            VoteStrategy::OneMin => write!(f, "onemin"),
+            VoteStrategy::Equal => write!(f, "equal"),
+            VoteStrategy::MinEqual => write!(f, "minequal"),
+            VoteStrategy::Nearest => write!(f, "nearest"),
+            VoteStrategy::Information => write!(f, "information"),
+            VoteStrategy::Swiss => write!(f, "swiss"),
         }
     }
 }
@@ -51,86 +80,369 @@ impl TryFrom<&str> for VoteStrategy {
             "equal" => Ok(VoteStrategy::Equal),
             "minequal" => Ok(VoteStrategy::MinEqual),
             "nearest" => Ok(VoteStrategy::Nearest),
+            "information" => Ok(VoteStrategy::Information),
+            "swiss" => Ok(VoteStrategy::Swiss),
             _ => Err(Error::ArgError),
         }
     }
 }
 
-pub(crate) fn vote<F: FnMut(&mut RelVec) -> Option<(usize, usize)> + Sized>(
-    input: &str,
-    output: &str,
-    rounds: u32,
-    mut choose: F,
-    info: bool,
-) -> Result<(), Error> {
+/// Settings of a `vote` session
+pub(crate) struct VoteOptions {
+    pub rounds: u32,
+    pub strategy: VoteStrategy,
+    pub repeat: RepeatPolicy,
+    pub ranking: Ranking,
+    /// Number of entries shown at once
+    pub items: usize,
+    pub info: bool,
+    /// Use the full-screen prompt if the terminal supports it
+    pub tui: bool,
+    /// Start a new Swiss-system tournament
+    pub restart: bool,
+    pub overrides: RatingOverrides,
+    pub voter: Option<String>,
+    /// Votes and time between two autosaves
+    pub autosave: (usize, Option<Duration>),
+}
+
+pub(crate) fn vote(input: &str, output: Output, options: VoteOptions) -> Result<(), Error> {
+    let VoteOptions {
+        rounds,
+        strategy,
+        repeat,
+        ranking,
+        items,
+        info,
+        tui,
+        restart,
+        overrides,
+        voter,
+        autosave,
+    } = options;
+
     let mut rv = RelVec::load(input)?;
-    let reader = io::stdin();
+    overrides.apply(&mut rv.config);
+    rv.voter = voter;
+    rv.repeat = repeat;
+    if restart {
+        rv.swiss = None;
+    }
+    let mut journal = Journal::new(Path::new(output.path), &rv, autosave.0, autosave.1);
+    journal.recover(&mut rv)?;
+    let mut choose = strategy.choose_function(ranking);
+    let strategy = strategy.to_string();
+
+    let mut prompt = prompt(tui, input, ranking, info)?;
 
-    for _ in 0..rounds {
-        let (a, b) = match choose(&mut rv) {
+    let found = if items > 2 {
+        vote_group(
+            &mut rv,
+            prompt.as_mut(),
+            &mut choose,
+            &mut journal,
+            rounds,
+            items,
+            &strategy,
+        )?
+    } else {
+        vote_pairs(
+            &mut rv,
+            prompt.as_mut(),
+            &mut choose,
+            &mut journal,
+            rounds,
+            &strategy,
+        )?
+    };
+    drop(prompt);
+    if !found {
+        let kind = if items > 2 { "group" } else { "pair" };
+        println!("There is no matching {}.", kind);
+    }
+
+    rv.close_period();
+    rv.save_to(output)?;
+    journal.finish()
+}
+
+/// Votes between two entries at a time. Returns `false` if there was no pair
+/// to vote on.
+pub(crate) fn vote_pairs(
+    rv: &mut RelVec,
+    prompt: &mut dyn Prompt,
+    choose: &mut ChooseFn,
+    journal: &mut Journal,
+    rounds: u32,
+    strategy: &str,
+) -> Result<bool, Error> {
+    // Only decisions of this session can be undone
+    let session = rv.history.len();
+    let mut redo: Vec<Record> = Vec::new();
+    let mut next = None;
+    let mut round = 0;
+
+    while round < rounds {
+        journal.update(rv)?;
+        let (a, b) = match next.take().or_else(|| choose(rv)) {
             Some((a, b)) => (a, b),
-            None => {
-                println!("There is no matching pair.");
-                return rv.save(output);
-            }
+            None => return Ok(false),
         };
 
-        if info {
-            println!(
-                "\x1b[34m(1)\x1b[0m {} \x1b[90m({}/{} = {}%)\x1b[0m",
-                rv[a].name,
-                rv[a].wins,
-                rv[a].votes,
-                rv[a].percentage()
-            );
-            println!("      \x1b[31;4mvs.\x1b[0m");
-            println!(
-                "\x1b[34m(2)\x1b[0m {} \x1b[90m({}/{} = {}%)\x1b[0m",
-                rv[b].name,
-                rv[b].wins,
-                rv[b].votes,
-                rv[b].percentage()
-            );
-        } else {
-            println!("\x1b[34m(1)\x1b[0m {}", rv[a].name);
-            println!("      vs.");
-            println!("\x1b[34m(2)\x1b[0m {}", rv[b].name);
+        let mut commands = vec![
+            ('!', "Clearly better, as 1! or 2!".to_owned()),
+            ('=', "Equally good".to_owned()),
+            ('o', "Can't decide".to_owned()),
+            ('x', format!("Remove {}", rv[a].name)),
+            ('y', format!("Remove {}", rv[b].name)),
+        ];
+        if rv.history.len() > session {
+            commands.push(('u', "Undo".to_owned()));
         }
-        println!();
-        println!("\x1b[35mo\x1b[0m - Can't decide");
-        println!("\x1b[35mx\x1b[0m - Remove {}", rv[a].name);
-        println!("\x1b[35my\x1b[0m - Remove {}", rv[b].name);
-        println!("\x1b[35mq\x1b[0m - Quit");
-        print!("$ ");
-
-        io::stdout().flush()?;
-
-        let mut cmd = String::new();
-        let _s = reader.read_line(&mut cmd)?;
-
-        if let Some(c) = cmd.chars().next() {
-            if c == '1' {
-                rv[a].wins += 1;
-                rv[a].votes += 1;
-                rv[b].votes += 1;
-            } else if c == '2' {
-                rv[b].wins += 1;
-                rv[a].votes += 1;
-                rv[b].votes += 1;
-            } else if c == 'o' {
-            } else if c == 'x' {
-                (*rv).remove(a);
-            } else if c == 'y' {
-                (*rv).remove(b);
+        if !redo.is_empty() {
+            commands.push(('r', "Redo".to_owned()));
+        }
+        commands.push(('q', "Quit".to_owned()));
+
+        let answer = match prompt.ask(rv, a, b, &commands, (round, rounds))? {
+            Some(answer) => answer,
+            None => return Ok(true),
+        };
+        if let Some((c, margin)) = parse_answer(&answer) {
+            let outcome = match c {
+                '1' => Some(Outcome::First),
+                '2' => Some(Outcome::Second),
+                '=' => Some(Outcome::Draw),
+                'o' => Some(Outcome::Skip),
+                'x' => Some(Outcome::RemoveFirst),
+                'y' => Some(Outcome::RemoveSecond),
+                _ => None,
+            };
+
+            if let Some(outcome) = outcome {
+                rv.decide_by(a, b, outcome, margin, strategy);
+                redo.clear();
+            } else if c == 'u' {
+                if rv.history.len() > session {
+                    if let Some(record) = rv.undo() {
+                        prompt.message(&format!("\x1b[33mUndone:\x1b[0m {}", record));
+                        // Ask the undone pair again
+                        next = rv.position(&record.a).zip(rv.position(&record.b));
+                        redo.push(record);
+                        round = round.saturating_sub(1);
+                    }
+                } else {
+                    prompt.message("\x1b[31mnothing to undo\x1b[0m");
+                    next = Some((a, b));
+                }
+                continue;
+            } else if c == 'r' {
+                match redo.pop() {
+                    Some(record) => {
+                        if rv.redo(&record).is_some() {
+                            prompt.message(&format!("\x1b[33mRedone:\x1b[0m {}", record));
+                        }
+                    }
+                    None => {
+                        prompt.message("\x1b[31mnothing to redo\x1b[0m");
+                        next = Some((a, b));
+                        continue;
+                    }
+                }
             } else if c == 'q' {
-                return rv.save(output);
+                return Ok(true);
             } else {
-                println!("\x1b[31munknown command\x1b[0m");
+                prompt.message("\x1b[31munknown command\x1b[0m");
             }
         }
 
-        println!("======================");
+        round += 1;
+    }
+
+    Ok(true)
+}
+
+/// Command of an answer and the margin of the decision, `1!` and `2!` mean
+/// clearly better
+fn parse_answer(answer: &str) -> Option<(char, u32)> {
+    let answer = answer.trim();
+    let c = answer.chars().next()?;
+
+    match (c, &answer[c.len_utf8()..]) {
+        ('1' | '2', "!") => Some((c, CLEAR)),
+        _ => Some((c, 1)),
+    }
+}
+
+/// Asks which entry of a pair is better
+pub(crate) trait Prompt {
+    /// Shows the pair and reads the answer: `1` or `2` for the entries,
+    /// followed by `!` if clearly better, or one of `commands`. `progress` is
+    /// the number of finished and of all rounds.
+    fn ask(
+        &mut self,
+        rv: &RelVec,
+        a: usize,
+        b: usize,
+        commands: &[(char, String)],
+        progress: (u32, u32),
+    ) -> Result<Option<String>, Error>;
+
+    /// Shows more than two entries and reads the answer: the number of the
+    /// best entry, a ranking like `3142` or one of `commands`
+    fn ask_group(
+        &mut self,
+        rv: &RelVec,
+        items: &[usize],
+        commands: &[(char, String)],
+        progress: (u32, u32),
+    ) -> Result<Option<String>, Error>;
+
+    /// Shows a message about the last command
+    fn message(&mut self, message: &str);
+}
+
+/// The full-screen prompt if it's wanted and the terminal supports it, else
+/// the line prompt
+pub(crate) fn prompt(
+    tui: bool,
+    title: &str,
+    ranking: Ranking,
+    info: bool,
+) -> Result<Box<dyn Prompt>, Error> {
+    if tui && Tui::available() {
+        Ok(Box::new(Tui::new(title, ranking, info)?))
+    } else {
+        Ok(Box::new(LinePrompt::new(info)))
+    }
+}
+
+/// Prompt reading whole lines, the default and the one for piped input
+pub(crate) struct LinePrompt {
+    info: bool,
+    first: bool,
+}
+
+impl LinePrompt {
+    pub fn new(info: bool) -> Self {
+        Self { info, first: true }
+    }
+}
+
+impl Prompt for LinePrompt {
+    fn ask(
+        &mut self,
+        rv: &RelVec,
+        a: usize,
+        b: usize,
+        commands: &[(char, String)],
+        _progress: (u32, u32),
+    ) -> Result<Option<String>, Error> {
+        if !self.first {
+            println!("======================");
+        }
+        self.first = false;
+
+        show_pair(rv, a, b, self.info);
+        read_line(commands)
+    }
+
+    fn ask_group(
+        &mut self,
+        rv: &RelVec,
+        items: &[usize],
+        commands: &[(char, String)],
+        _progress: (u32, u32),
+    ) -> Result<Option<String>, Error> {
+        if !self.first {
+            println!("======================");
+        }
+        self.first = false;
+
+        for (n, &i) in items.iter().enumerate() {
+            println!("\x1b[34m({})\x1b[0m {}", n + 1, entry(rv, i, self.info));
+        }
+        println!();
+        println!(
+            "Type the number of the best entry or rank them all, e.g. \x1b[35m{}\x1b[0m",
+            (1..=items.len())
+                .rev()
+                .map(|n| n.to_string())
+                .collect::<String>()
+        );
+
+        Ok(read_line(commands)?.map(|l| l.trim().to_owned()))
+    }
+
+    fn message(&mut self, message: &str) {
+        println!("{}", message);
     }
+}
+
+/// Name of an entry, with its statistics if `info` is set
+pub(crate) fn entry(rv: &RelVec, i: usize, info: bool) -> String {
+    if info {
+        format!(
+            "{} \x1b[90m({}/{} = {}%, {:.1} Elo)\x1b[0m",
+            rv[i].name,
+            rv[i].wins,
+            rv[i].votes,
+            rv[i].percentage(),
+            rv[i].elo_rating(&rv.config.elo)
+        )
+    } else {
+        rv[i].name.clone()
+    }
+}
+
+/// Prints a pair of entries
+pub(crate) fn show_pair(rv: &RelVec, a: usize, b: usize, info: bool) {
+    if info {
+        println!("\x1b[34m(1)\x1b[0m {}", entry(rv, a, info));
+        println!("      \x1b[31;4mvs.\x1b[0m");
+        println!("\x1b[34m(2)\x1b[0m {}", entry(rv, b, info));
+    } else {
+        println!("\x1b[34m(1)\x1b[0m {}", rv[a].name);
+        println!("      vs.");
+        println!("\x1b[34m(2)\x1b[0m {}", rv[b].name);
+    }
+}
+
+/// Prints the available commands besides `1` and `2` and reads the chosen one
+pub(crate) fn read_command(commands: &[(char, String)]) -> Result<Option<char>, Error> {
+    Ok(read_line(commands)?.and_then(|l| l.chars().next()))
+}
 
-    rv.save(output)
+/// Prints the available commands and reads a line, `None` at the end of the input
+fn read_line(commands: &[(char, String)]) -> Result<Option<String>, Error> {
+    println!();
+    for (key, description) in commands {
+        println!("\x1b[35m{}\x1b[0m - {}", key, description);
+    }
+    print!("$ ");
+
+    io::stdout().flush()?;
+
+    let mut cmd = String::new();
+    if io::stdin().read_line(&mut cmd)? == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(cmd))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_answer;
+    use crate::history::CLEAR;
+
+    #[test]
+    fn vote_parse_answer() {
+        assert_eq!(parse_answer("1\n"), Some(('1', 1)));
+        assert_eq!(parse_answer("2!\n"), Some(('2', CLEAR)));
+        assert_eq!(parse_answer(" 1!"), Some(('1', CLEAR)));
+        assert_eq!(parse_answer("=!"), Some(('=', 1)));
+        assert_eq!(parse_answer("q"), Some(('q', 1)));
+        assert_eq!(parse_answer(""), None);
+    }
 }